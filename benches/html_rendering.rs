@@ -0,0 +1,40 @@
+//! Compares how long each way of rendering HTML takes on a big step tree.
+//! Run it with `cargo bench --bench html_rendering`.
+
+use criterion::{criterion_group, criterion_main, Criterion};
+
+use math_thing::{
+    math, simplify_excess_parens, AsExpression, Constant, Expression, PrintOpts, PrintTarget,
+    StepPrintOpts,
+};
+
+/// Builds an expression of nested sums and products that is `depth` levels deep.
+fn nested_expression(depth: usize) -> Expression {
+    let x = Constant::new("x");
+    let pi = Constant::new(r"\pi");
+    if depth == 0 {
+        return math![x + pi].expr();
+    }
+    let inner = nested_expression(depth - 1);
+    match depth % 2 {
+        0 => math![(x * { inner.clone() }) + { inner }].expr(),
+        _ => math![(pi + { inner.clone() }) * { inner }].expr(),
+    }
+}
+
+fn html_rendering(c: &mut Criterion) {
+    let steps = simplify_excess_parens(&nested_expression(6));
+    let mut group = c.benchmark_group("html_rendering");
+    for (name, target) in [
+        ("KaTeX", PrintTarget::LaTex),
+        ("MathML", PrintTarget::MathML),
+        ("HTML text", PrintTarget::MathPrint),
+    ] {
+        let print_opts = StepPrintOpts::uniform(PrintOpts::default_for(target));
+        group.bench_function(name, |b| b.iter(|| steps.html_print(target, &print_opts)));
+    }
+    group.finish();
+}
+
+criterion_group!(benches, html_rendering);
+criterion_main!(benches);
@@ -0,0 +1,16 @@
+//! Checks that KaTeX's files are downloaded before the `embed-katex` feature embeds them.
+
+use std::path::Path;
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed=vendor/katex");
+    if std::env::var_os("CARGO_FEATURE_EMBED_KATEX").is_some()
+        && !Path::new("vendor/katex/katex.min.css").exists()
+    {
+        panic!(
+            "The `embed-katex` feature embeds KaTeX's stylesheet and fonts, \
+             run `vendor/katex/download.sh` to download them first"
+        );
+    }
+}
@@ -3,27 +3,58 @@ import katex from "katex";
 import "katex/contrib/copy-tex";
 import "katex/dist/katex.min.css";
 import "./app.css";
+import { renderSteps } from "./wasm/math_thing";
 
-export function App() {
-  const mathRaw = useSignal(
-    // String.raw`\cos(\theta)=\frac{e^{i\theta}+e^{-i\theta}}{2}`
-    // String.raw`-\frac{\hbar^2}{2m}\frac{\partial^2\psi}{\partial x^2} = i\hbar\frac{\partial \psi}{\partial t}`
-    String.raw`-\frac{\hbar^2}{2m}\htmlClass{hl}{\frac{\partial^2\psi}{\partial x^2}} = \htmlClass{hl}{i\hbar}\frac{\partial \psi}{\htmlClass{hl}{\partial t}}`
+/** `#/$defs/renderedExpression` in `schema/step.schema.json` */
+interface RenderedExpression {
+  latex: string;
+}
+
+/** `#/$defs/step` in `schema/step.schema.json` */
+interface Step {
+  label?: string;
+  annotatedExpression?: RenderedExpression;
+  substeps: Step[];
+  result: RenderedExpression;
+}
+
+function Math({ expression }: { expression: RenderedExpression }) {
+  const html = katex.renderToString(expression.latex, {
+    throwOnError: true,
+    displayMode: true,
+    trust: true,
+  });
+  return <div dangerouslySetInnerHTML={{ __html: html }} />;
+}
+
+function StepView({ step }: { step: Step }) {
+  return (
+    <>
+      <details open>
+        <summary>{step.label}</summary>
+        <div class="substeps">
+          {step.annotatedExpression && (
+            <Math expression={step.annotatedExpression} />
+          )}
+          {step.substeps.map((substep) => (
+            <StepView step={substep} />
+          ))}
+        </div>
+      </details>
+      <Math expression={step.result} />
+    </>
   );
+}
+
+export function App() {
+  const input = useSignal("(a + b) + ((x * y) * (z * (x * y)))");
 
-  const mathOutput = useComputed(() => {
+  const steps = useComputed(() => {
     try {
-      const result = katex.renderToString(mathRaw.value, {
-        throwOnError: true,
-        displayMode: true,
-        trust: true,
-      });
-      return { success: true, data: result } as const;
+      const step: Step = JSON.parse(renderSteps(input.value, "json"));
+      return { success: true, data: step } as const;
     } catch (error) {
-      return {
-        success: false,
-        data: error as import("katex").ParseError,
-      } as const;
+      return { success: false, data: error as Error } as const;
     }
   });
 
@@ -31,22 +62,16 @@ export function App() {
     <div class="app">
       <input
         class="math-input"
-        value={mathRaw}
+        value={input}
         onInput={(el) => {
-          mathRaw.value = el.currentTarget.value;
+          input.value = el.currentTarget.value;
         }}
       />
       <div class="math-output">
-        {mathOutput.value.success ? (
-          <div dangerouslySetInnerHTML={{ __html: mathOutput.value.data }} />
+        {steps.value.success ? (
+          <StepView step={steps.value.data} />
         ) : (
-          <>
-            <div>{mathOutput.value.data.message}</div>
-            <pre>
-              <code>{`${mathRaw}
-${" ".repeat(mathOutput.value.data.position)}^`}</code>
-            </pre>
-          </>
+          <div>{steps.value.data.message}</div>
         )}
       </div>
     </div>
@@ -1,5 +1,8 @@
 import { render } from 'preact'
 import { App } from './app'
 import './index.css'
+import init from './wasm/math_thing'
 
-render(<App />, document.getElementById('app') as HTMLElement)
+init().then(() => {
+  render(<App />, document.getElementById('app') as HTMLElement)
+})
@@ -0,0 +1,226 @@
+//! Python bindings, for generating problem sets from Python.
+//!
+//! Build and install into the current virtualenv with [maturin](https://www.maturin.rs),
+//! then run the tests in `tests/`:
+//!
+//! ```sh
+//! cd python
+//! maturin develop --extras test
+//! pytest
+//! ```
+//!
+//! ```python
+//! from math_thing import Expression, parse
+//!
+//! x, y, z = (Expression.constant(name) for name in "xyz")
+//! steps = ((x * y) * z).simplify()
+//! print(steps.result)  # x * y * z
+//! print(parse("(x + y) + z").simplify().latex())
+//! ```
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+use math_thing::{
+    html_document, step_to_json, AsExpression, Bindings, Expression, HtmlOptions, Negation,
+    PrintOpts, PrintTarget, Product, Step, StepPrintOpts, Sum,
+};
+
+/// An expression: a constant, or a sum, product or negation of expressions.
+///
+/// `+`, `-` and `*` build new expressions, keeping their operands grouped the way
+/// parentheses do, so `(x * y) * z` is a product inside a product.
+#[pyclass(name = "Expression", module = "math_thing", unsendable)]
+#[derive(Clone)]
+struct PyExpression(Expression);
+
+thread_local! {
+    /// The constants made by `Expression.constant`, by name, so each name is one constant
+    /// the way it is within a parsed expression.
+    static CONSTANTS: RefCell<HashMap<String, Expression>> = RefCell::default();
+}
+
+/// A step in simplifying an expression, and the steps it was made of.
+#[pyclass(name = "Step", module = "math_thing", unsendable)]
+struct PyStep {
+    /// The outermost step, which owns this one.
+    root: Rc<Step>,
+    /// The indices of the substeps leading from `root` to this step.
+    path: Vec<usize>,
+}
+
+#[pymethods]
+impl PyExpression {
+    /// A constant, like `x`, `π` or `\pi`.
+    #[staticmethod]
+    fn constant(name: &str) -> PyResult<Self> {
+        let Ok(Expression::Constant(constant)) = math_thing::parse(name) else {
+            return Err(PyValueError::new_err(format!("{name:?} isn't a constant")));
+        };
+        Ok(Self(CONSTANTS.with_borrow_mut(|constants| {
+            constants
+                .entry(constant.name().to_owned())
+                .or_insert_with(|| Expression::Constant(constant))
+                .clone()
+        })))
+    }
+
+    #[staticmethod]
+    fn sum(terms: Vec<PyExpression>) -> Self {
+        Self(Sum::new(terms.into_iter().map(|term| term.0).collect()).expr())
+    }
+
+    #[staticmethod]
+    fn product(terms: Vec<PyExpression>) -> Self {
+        Self(Product::new(terms.into_iter().map(|term| term.0).collect()).expr())
+    }
+
+    #[staticmethod]
+    fn negation(inner: PyExpression) -> Self {
+        Self(Negation::new(inner.0).expr())
+    }
+
+    fn __add__(&self, other: PyExpression) -> Self {
+        Self::sum(vec![self.clone(), other])
+    }
+
+    fn __sub__(&self, other: PyExpression) -> Self {
+        Self::sum(vec![self.clone(), Self::negation(other)])
+    }
+
+    fn __mul__(&self, other: PyExpression) -> Self {
+        Self::product(vec![self.clone(), other])
+    }
+
+    fn __neg__(&self) -> Self {
+        Self::negation(self.clone())
+    }
+
+    fn __str__(&self) -> String {
+        self.0.to_string()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("parse({:?})", self.0.to_string())
+    }
+
+    fn latex(&self) -> String {
+        self.0.render(
+            PrintTarget::LaTex,
+            &PrintOpts::default_for(PrintTarget::LaTex),
+        )
+    }
+
+    /// Evaluates the expression, with `values` for its constants by name.
+    /// `π` and `e` have their usual values unless given.
+    #[pyo3(signature = (values = None))]
+    fn evaluate(&self, values: Option<HashMap<String, f64>>) -> PyResult<f64> {
+        let bindings = values
+            .unwrap_or_default()
+            .iter()
+            .fold(Bindings::new(), |bindings, (name, &value)| {
+                bindings.bind(name, value)
+            });
+        self.0
+            .evaluate(&bindings)
+            .map_err(|error| PyValueError::new_err(error.to_string()))
+    }
+
+    /// Simplifies the expression, returning the steps taken.
+    fn simplify(&self) -> PyStep {
+        PyStep {
+            root: Rc::new(math_thing::simplify(&self.0)),
+            path: vec![],
+        }
+    }
+}
+
+impl PyStep {
+    fn step(&self) -> &Step {
+        self.path
+            .iter()
+            .fold(self.root.as_ref(), |step, &index| &step.substeps[index])
+    }
+}
+
+#[pymethods]
+impl PyStep {
+    #[getter]
+    fn label(&self) -> Option<String> {
+        self.step().label.clone()
+    }
+
+    /// The expression after this step.
+    #[getter]
+    fn result(&self) -> PyExpression {
+        PyExpression(self.step().result.clone())
+    }
+
+    #[getter]
+    fn substeps(&self) -> Vec<PyStep> {
+        (0..self.step().substeps.len())
+            .map(|index| PyStep {
+                root: self.root.clone(),
+                path: [self.path.as_slice(), &[index]].concat(),
+            })
+            .collect()
+    }
+
+    /// The steps as text, wrapping expressions to fit in `width` characters if it's given.
+    #[pyo3(signature = (width = None))]
+    fn text(&self, width: Option<usize>) -> String {
+        self.step().math_print(&StepPrintOpts::uniform(PrintOpts {
+            line_width: width,
+            ..PrintOpts::default_for(PrintTarget::MathPrint)
+        }))
+    }
+
+    fn latex(&self) -> String {
+        self.step()
+            .latex_print(&StepPrintOpts::uniform(PrintOpts::default_for(
+                PrintTarget::LaTex,
+            )))
+    }
+
+    /// A standalone HTML page showing the steps, with the expressions as MathML.
+    fn html(&self) -> String {
+        html_document(self.step(), &HtmlOptions::new(PrintTarget::MathML))
+    }
+
+    /// The steps as JSON matching `schema/step.schema.json`.
+    fn json(&self) -> String {
+        step_to_json(self.step())
+    }
+
+    fn __str__(&self) -> String {
+        self.step().to_string()
+    }
+}
+
+/// Parses an infix expression, like `-(x + y) * z`.
+#[pyfunction]
+fn parse(input: &str) -> PyResult<PyExpression> {
+    math_thing::parse(input)
+        .map(PyExpression)
+        .map_err(|error| PyValueError::new_err(error.to_string()))
+}
+
+/// Simplifies an expression, returning the steps taken.
+#[pyfunction]
+fn simplify(expression: &PyExpression) -> PyStep {
+    expression.simplify()
+}
+
+#[pymodule]
+#[pyo3(name = "math_thing")]
+fn python_module(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<PyExpression>()?;
+    module.add_class::<PyStep>()?;
+    module.add_function(wrap_pyfunction!(parse, module)?)?;
+    module.add_function(wrap_pyfunction!(simplify, module)?)?;
+    Ok(())
+}
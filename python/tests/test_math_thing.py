@@ -0,0 +1,47 @@
+import json
+
+import pytest
+
+from math_thing import Expression, parse, simplify
+
+
+def test_operators_build_nested_expressions():
+    x, y, z = (Expression.constant(name) for name in "xyz")
+    assert str((x * y) * z) == "(x * y) * z"
+    assert str(x * y * -z) == "(x * y) * (-z)"
+    assert str(x - y + z) == "(x - y) + z"
+    assert str(-(x + y)) == "-(x + y)"
+
+
+def test_constants_with_the_same_name_are_the_same():
+    x = Expression.constant("x")
+    steps = simplify(x + Expression.constant("x"))
+    first, second = json.loads(steps.json())["result"]["expression"]["terms"]
+    assert first["id"] == second["id"]
+
+
+def test_simplify():
+    steps = simplify(parse("(x * y) * z"))
+    assert str(steps.result) == "x * y * z"
+    assert [step.label for step in steps.substeps] == [
+        "Remove excess parentheses around product"
+    ]
+    assert "(x * y) * z" in steps.text()
+    assert r"\left(\htmlClass{hl}{x y}\right) z" in steps.latex()
+    assert "<math>" in steps.html()
+    assert json.loads(steps.json())["result"]["mathPrint"] == "x * y * z"
+
+
+def test_evaluate():
+    expression = parse("x * (y + z)")
+    assert expression.evaluate({"x": 2, "y": 3, "z": 4}) == 14
+    assert parse("2 * π").evaluate({"2": 2}) == pytest.approx(6.283185307179586)
+    with pytest.raises(ValueError, match="No value was given for y"):
+        parse("x * y").evaluate({"x": 1})
+
+
+def test_errors():
+    with pytest.raises(ValueError, match="Unexpected"):
+        parse("x + * y")
+    with pytest.raises(ValueError):
+        Expression.constant("x + y")
@@ -1,35 +1,125 @@
+use serde::{Deserialize, Serialize};
+
 use crate::{
     expression::{Expression, ExpressionId, DEFAULT_PRINT_OPTS},
-    token_stream::TokenStream,
-    PrintOpts, Printable,
+    token_stream::{print_for_target, TokenStream},
+    PrintOpts, PrintTarget, Printable,
 };
 
-pub(crate) struct AnnotatedExpression {
-    pub(crate) expression: Expression,
-    pub(crate) annotations: Vec<Annotation>,
+/// An expression with parts of it highlighted.
+pub struct AnnotatedExpression {
+    pub expression: Expression,
+    pub annotations: Vec<Annotation>,
+}
+
+/// Highlights the node with the id `target_id`.
+pub struct Annotation {
+    pub target_id: ExpressionId,
+    pub kind: AnnotationKind,
+    /// Text placed under the highlighted expression.
+    pub label: Option<String>,
+    /// Which of its step's changes the annotation belongs to, counting from 0.
+    /// A part that was removed and the parts that replaced it belong to the same change.
+    pub change: Option<usize>,
 }
 
-pub(crate) struct Annotation {
-    pub(crate) target_id: ExpressionId,
+/// What an annotation means, which decides how it is drawn.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum AnnotationKind {
+    /// The part of the expression a step is about to change.
+    #[default]
+    Focus,
+    /// Something that was added or that replaced something else.
+    Added,
+    /// Something that is about to be removed.
+    Removed,
+    /// One of a pair of terms that cancel each other out.
+    CancelledA,
+    /// The other term of a cancelled pair.
+    CancelledB,
+}
+
+impl AnnotationKind {
+    /// The character used to underline annotated text in MathPrint.
+    pub(crate) fn marker(self) -> char {
+        match self {
+            AnnotationKind::Focus => '^',
+            AnnotationKind::Added => '~',
+            AnnotationKind::Removed => '-',
+            AnnotationKind::CancelledA => '/',
+            AnnotationKind::CancelledB => '\\',
+        }
+    }
+    /// The class given to annotated elements in HTML and MathML.
+    pub(crate) fn css_class(self) -> &'static str {
+        match self {
+            AnnotationKind::Focus => "hl",
+            AnnotationKind::Added => "hl-added",
+            AnnotationKind::Removed => "hl-removed",
+            AnnotationKind::CancelledA => "hl-cancelled-a",
+            AnnotationKind::CancelledB => "hl-cancelled-b",
+        }
+    }
+    /// The fill color of Typst highlights, or `None` for Typst's default.
+    pub(crate) fn typst_fill(self) -> Option<&'static str> {
+        match self {
+            AnnotationKind::Focus => None,
+            AnnotationKind::Added => Some("rgb(\"#9be9a8\")"),
+            AnnotationKind::Removed => Some("rgb(\"#ffaba8\")"),
+            AnnotationKind::CancelledA => Some("rgb(\"#a8d1ff\")"),
+            AnnotationKind::CancelledB => Some("rgb(\"#d8b9ff\")"),
+        }
+    }
 }
 
 impl Annotation {
-    pub(crate) fn new(expr: &Expression) -> Self {
+    pub fn new(expr: &Expression) -> Self {
         Self {
             target_id: expr.id(),
+            kind: AnnotationKind::Focus,
+            label: None,
+            change: None,
+        }
+    }
+    pub fn with_kind(self, kind: AnnotationKind) -> Self {
+        Self { kind, ..self }
+    }
+    pub fn with_label(self, label: impl Into<String>) -> Self {
+        Self {
+            label: Some(label.into()),
+            ..self
+        }
+    }
+    pub fn with_change(self, change: usize) -> Self {
+        Self {
+            change: Some(change),
+            ..self
         }
     }
 }
 
 impl AnnotatedExpression {
-    pub(crate) fn print<'a>(&'a self, print_opts: &'a PrintOpts) -> TokenStream {
-        self.expression.print(print_opts, &self.annotations)
+    pub(crate) fn print<'a>(
+        &'a self,
+        target: PrintTarget,
+        print_opts: &'a PrintOpts,
+    ) -> TokenStream {
+        self.expression.print(target, print_opts, &self.annotations)
+    }
+    /// Prints the expression with its annotations for the target.
+    pub fn render(&self, target: PrintTarget, print_opts: &PrintOpts) -> String {
+        print_for_target(&self.print(target, print_opts), target, print_opts)
     }
 }
 
 impl std::fmt::Display for AnnotatedExpression {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.print(&DEFAULT_PRINT_OPTS))
+        write!(
+            f,
+            "{}",
+            self.print(PrintTarget::MathPrint, &DEFAULT_PRINT_OPTS)
+        )
     }
 }
 
@@ -37,7 +127,12 @@ impl std::fmt::Display for AnnotatedExpression {
 mod tests {
     use insta::assert_display_snapshot;
 
-    use crate::{constant::Constant, expression::AsExpression, math};
+    use crate::{
+        constant::Constant,
+        expression::AsExpression,
+        math,
+        token_stream::{latex_print, mathml_print, typst_print},
+    };
 
     use super::*;
 
@@ -58,4 +153,45 @@ mod tests {
          ^^^^^
         "###);
     }
+
+    #[test]
+    fn test_annotation_kinds_and_labels() {
+        let x = Constant::new("x");
+        let y = Constant::new("y");
+        let z = Constant::new("z");
+        let removed = math![x * y].expr();
+        let added = math![y * z].expr();
+        let cancelled_a = math![(-(x * z))].expr();
+        let cancelled_b = math![x * z].expr();
+        let exp = math![
+            { removed.clone() } + { added.clone() } + { cancelled_a.clone() } + {
+                cancelled_b.clone()
+            }
+        ]
+        .expr();
+        let annotated_exp = AnnotatedExpression {
+            expression: exp,
+            annotations: vec![
+                Annotation::new(&removed)
+                    .with_kind(AnnotationKind::Removed)
+                    .with_label("old"),
+                Annotation::new(&added)
+                    .with_kind(AnnotationKind::Added)
+                    .with_label("new term"),
+                Annotation::new(&cancelled_a).with_kind(AnnotationKind::CancelledA),
+                Annotation::new(&cancelled_b)
+                    .with_kind(AnnotationKind::CancelledB)
+                    .with_label("cancels"),
+            ],
+        };
+        assert_display_snapshot!(annotated_exp, @r###"
+        x * y + y * z - x * z + x * z
+        -----   ~~~~~ ///////   \\\\\
+        old     new term        cancels
+        "###);
+        let print = |target| annotated_exp.print(target, &PrintOpts::default_for(target));
+        insta::assert_snapshot!(latex_print(&print(PrintTarget::LaTex)), @r###"\htmlClass{hl-removed}{\underbrace{x y}_{\text{old}}}+\htmlClass{hl-added}{\underbrace{y z}_{\text{new term}}}\htmlClass{hl-cancelled-a}{-x z}+\htmlClass{hl-cancelled-b}{\underbrace{x z}_{\text{cancels}}}"###);
+        insta::assert_snapshot!(mathml_print(&print(PrintTarget::MathML)), @r###"<math><munder><mrow class="hl-removed"><mi>x</mi><mo>&#x2062;</mo><mi>y</mi></mrow><mtext>old</mtext></munder><mo>+</mo><munder><mrow class="hl-added"><mi>y</mi><mo>&#x2062;</mo><mi>z</mi></mrow><mtext>new term</mtext></munder><mrow class="hl-cancelled-a"><mo>-</mo><mi>x</mi><mo>&#x2062;</mo><mi>z</mi></mrow><mo>+</mo><munder><mrow class="hl-cancelled-b"><mi>x</mi><mo>&#x2062;</mo><mi>z</mi></mrow><mtext>cancels</mtext></munder></math>"###);
+        insta::assert_snapshot!(typst_print(&print(PrintTarget::Typst)), @r###"underbrace(#highlight(fill: rgb("#ffaba8"))[$x y$], "old") + underbrace(#highlight(fill: rgb("#9be9a8"))[$y z$], "new term")#highlight(fill: rgb("#a8d1ff"))[$ - x z$] + underbrace(#highlight(fill: rgb("#d8b9ff"))[$x z$], "cancels")"###);
+    }
 }
@@ -0,0 +1,169 @@
+//! Simplifies a file of expressions, one per line, writing the steps for each to a directory.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use math_thing::{parse, simplify, PrintTarget};
+
+use crate::{parse_error, print_steps_with, CliError, Format, Options};
+
+/// How one line of the batch went.
+enum Outcome {
+    Simplified(String),
+    Unchanged(String),
+    Failed(CliError),
+}
+
+fn extension(format: Format) -> &'static str {
+    match format {
+        Format::Text => "txt",
+        Format::Latex => "tex",
+        Format::Typst => "typ",
+        Format::Html => "html",
+        Format::Json => "json",
+    }
+}
+
+/// Simplifies every non-empty line of `input`, writing `problem-N` files named after the line
+/// numbers and an `index.txt` summarizing them to `out`.
+/// Lines that fail don't stop the others, but make the batch fail at the end,
+/// with an I/O error if any steps couldn't be written.
+/// HTML is written with MathML, so every file doesn't embed its own copy of KaTeX's fonts.
+pub(crate) fn run_batch(input: &str, out: &Path, options: &Options) -> Result<String, CliError> {
+    fs::create_dir_all(out)
+        .map_err(|error| io::Error::new(error.kind(), format!("{}: {error}", out.display())))?;
+    let width = input.lines().count().to_string().len();
+    let mut index = String::new();
+    let (mut simplified, mut unchanged, mut failed, mut unwritten) = (0, 0, 0, 0);
+    for (i, line) in input.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let number = i + 1;
+        let file = format!("problem-{number:0width$}.{}", extension(options.format));
+        let outcome = match parse(line) {
+            Err(error) => Outcome::Failed(parse_error(line, error)),
+            Ok(expression) => {
+                let steps = simplify(&expression);
+                let text = print_steps_with(&steps, options, PrintTarget::MathML);
+                match fs::write(out.join(&file), text) {
+                    Err(error) => Outcome::Failed(CliError::Io(io::Error::new(
+                        error.kind(),
+                        format!("{file}: {error}"),
+                    ))),
+                    Ok(()) if steps.substeps.is_empty() => Outcome::Unchanged(file),
+                    Ok(()) => Outcome::Simplified(file),
+                }
+            }
+        };
+        index += &format!("{number:width$}. {line}\n");
+        let message = match outcome {
+            Outcome::Simplified(file) => {
+                simplified += 1;
+                format!("Simplified: {file}")
+            }
+            Outcome::Unchanged(file) => {
+                unchanged += 1;
+                format!("Already simplified: {file}")
+            }
+            Outcome::Failed(error) => {
+                match error {
+                    CliError::Io(_) => unwritten += 1,
+                    _ => failed += 1,
+                }
+                error.to_string()
+            }
+        };
+        for message_line in message.lines() {
+            index += &format!("{:width$}  {message_line}\n", "");
+        }
+    }
+    fs::write(out.join("index.txt"), &index)?;
+
+    let mut summary =
+        format!("{simplified} simplified, {unchanged} already simplified, {failed} failed");
+    if unwritten > 0 {
+        summary += &format!(", {unwritten} couldn't be written");
+    }
+    summary += &format!(", see {}", out.join("index.txt").display());
+    if unwritten > 0 {
+        Err(CliError::Io(io::Error::other(summary)))
+    } else if failed > 0 {
+        Err(CliError::Parse(summary))
+    } else {
+        Ok(summary)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_batch() {
+        let out =
+            std::env::temp_dir().join(format!("math-thing-test-batch-{}", std::process::id()));
+        let options = Options {
+            format: Format::Text,
+            line_width: None,
+        };
+        let input = "(x * y) * z\nx * y\n\nx * (y + ) * z\n";
+
+        let error = run_batch(input, &out, &options).unwrap_err();
+        assert_eq!(error.exit_code(), 3);
+        insta::assert_snapshot!(
+            fs::read_to_string(out.join("index.txt")).unwrap(),
+            @r###"
+        1. (x * y) * z
+           Simplified: problem-1.txt
+        2. x * y
+           Already simplified: problem-2.txt
+        4. x * (y + ) * z
+           Unexpected ')' at 9
+             x * (y + ) * z
+                      ^
+        "###
+        );
+        insta::assert_snapshot!(
+            fs::read_to_string(out.join("problem-1.txt")).unwrap(),
+            @r###"
+        Simplify excess parentheses
+          Remove excess parentheses around product
+            (x * y) * z
+             ^^^^^
+            x * y * z
+            ~   ~
+          x * y * z
+        "###
+        );
+        assert!(out.join("problem-2.txt").exists());
+        assert!(!out.join("problem-4.txt").exists());
+
+        let summary = run_batch("(x * y) * z", &out, &options).unwrap();
+        assert!(summary.starts_with("1 simplified, 0 already simplified, 0 failed"));
+
+        let html = Options {
+            format: Format::Html,
+            ..options
+        };
+        run_batch("(x * y) * z", &out, &html).unwrap();
+        let document = fs::read_to_string(out.join("problem-1.html")).unwrap();
+        assert!(document.contains("<math>"));
+        assert!(!document.contains("data:font/woff2"));
+
+        // Steps that can't be written are I/O errors, even when other lines don't parse
+        fs::remove_file(out.join("problem-1.txt")).unwrap();
+        fs::create_dir(out.join("problem-1.txt")).unwrap();
+        let error = run_batch("(x * y) * z\nx * y\nx * (y + ) * z", &out, &options).unwrap_err();
+        assert_eq!(error.exit_code(), 4);
+        assert!(error
+            .to_string()
+            .starts_with("0 simplified, 1 already simplified, 1 failed, 1 couldn't be written"));
+        assert!(fs::read_to_string(out.join("index.txt"))
+            .unwrap()
+            .contains("problem-1.txt: "));
+        fs::remove_dir_all(&out).unwrap();
+    }
+}
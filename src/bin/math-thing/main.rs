@@ -0,0 +1,440 @@
+use std::io::{self, Read};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+mod batch;
+mod repl;
+
+use math_thing::{
+    html_document, parse, simplify, step_from_json, step_to_json, verify, HtmlOptions, LoadError,
+    ParseError, PrintOpts, PrintTarget, Step, StepPrintOpts, UnsoundStep,
+};
+
+const USAGE: &str = "\
+Usage:
+  math-thing simplify [EXPRESSION | --file PATH] [OPTIONS]
+  math-thing render [STEPS_JSON] [OPTIONS]
+  math-thing verify [STEPS_JSON]
+  math-thing batch [PROBLEMS] --out DIR [OPTIONS]
+  math-thing repl
+
+Commands:
+  simplify  Simplifies an expression like `(x * y) * z`, printing the steps
+  render    Prints steps saved by `simplify --format json` in another format
+  verify    Checks that every step in saved steps keeps the expression's value
+  batch     Simplifies each line of a file of expressions, writing the steps for each
+            and an `index.txt` summary to DIR
+  repl      Starts an interactive session for exploring steps, type `help` in it for more
+
+Input is read from stdin when it isn't given, or is `-`.
+Expressions that look like options, like `-x`, can be given after `--`.
+
+Options:
+  -f, --format FORMAT   text (default), latex, typst, html or json
+  -w, --width WIDTH     Wraps long expressions to fit in WIDTH characters
+      --file PATH       Reads the expression to simplify from a file
+  -o, --out DIR         Where `batch` writes its files
+  -h, --help            Prints this message
+
+Exit codes:
+  0  Success
+  1  A step is unsound
+  2  Invalid arguments
+  3  The input, or a line of the batch, couldn't be parsed
+  4  A file couldn't be read or written
+";
+
+#[derive(Debug, PartialEq)]
+enum Input {
+    Argument(String),
+    File(PathBuf),
+    Stdin,
+}
+
+impl Input {
+    fn read(&self) -> io::Result<String> {
+        match self {
+            Input::Argument(text) => Ok(text.clone()),
+            Input::File(path) => std::fs::read_to_string(path).map_err(|error| {
+                io::Error::new(error.kind(), format!("{}: {error}", path.display()))
+            }),
+            Input::Stdin => {
+                let mut text = String::new();
+                io::stdin().read_to_string(&mut text)?;
+                Ok(text)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Format {
+    Text,
+    Latex,
+    Typst,
+    Html,
+    Json,
+}
+
+#[derive(Debug, PartialEq)]
+struct Options {
+    format: Format,
+    line_width: Option<usize>,
+}
+
+#[derive(Debug, PartialEq)]
+enum Command {
+    Simplify(Input, Options),
+    Render(Input, Options),
+    Verify(Input),
+    Batch(Input, PathBuf, Options),
+    Repl,
+    Help,
+}
+
+#[derive(Debug)]
+enum CliError {
+    Usage(String),
+    Parse(String),
+    Io(io::Error),
+    Unsound(UnsoundStep),
+}
+
+impl CliError {
+    fn exit_code(&self) -> u8 {
+        match self {
+            CliError::Unsound(_) => 1,
+            CliError::Usage(_) => 2,
+            CliError::Parse(_) => 3,
+            CliError::Io(_) => 4,
+        }
+    }
+}
+
+impl std::fmt::Display for CliError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CliError::Usage(message) => {
+                write!(f, "{message}\nRun `math-thing --help` for usage")
+            }
+            CliError::Parse(message) => write!(f, "{message}"),
+            CliError::Io(error) => write!(f, "{error}"),
+            CliError::Unsound(unsound) => write!(f, "{unsound}"),
+        }
+    }
+}
+
+impl From<io::Error> for CliError {
+    fn from(error: io::Error) -> Self {
+        CliError::Io(error)
+    }
+}
+
+impl From<LoadError> for CliError {
+    fn from(error: LoadError) -> Self {
+        CliError::Parse(format!("Invalid steps: {error}"))
+    }
+}
+
+/// Describes a parse error, pointing at where it happened in the input.
+fn parse_error(input: &str, error: ParseError) -> CliError {
+    match error {
+        ParseError::Unexpected(offset, _) if !input.contains('\n') => {
+            let column = input[..offset].chars().count();
+            CliError::Parse(format!("{error}\n  {input}\n  {}^", " ".repeat(column)))
+        }
+        _ => CliError::Parse(error.to_string()),
+    }
+}
+
+/// The value following an option like `--format`.
+fn value(args: &mut impl Iterator<Item = String>, flag: &str) -> Result<String, CliError> {
+    args.next()
+        .ok_or_else(|| CliError::Usage(format!("{flag} needs a value")))
+}
+
+/// Whether an argument is an option like `--width` or `-w`, rather than an expression like `-x * y`.
+/// Expressions that look like options, like `-x`, can be given after `--`.
+fn looks_like_option(arg: &str) -> bool {
+    let mut chars = arg.chars();
+    chars.next() == Some('-')
+        && match chars.next() {
+            Some('-') => true,
+            Some(c) => c.is_ascii_alphabetic() && chars.next().is_none(),
+            None => false,
+        }
+}
+
+fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Command, CliError> {
+    let mut args = args.into_iter();
+    let command = match args.next() {
+        Some(command) => command,
+        None => return Err(CliError::Usage("No command given".to_owned())),
+    };
+    let mut input = None;
+    let mut out = None;
+    let mut options = Options {
+        format: Format::Text,
+        line_width: None,
+    };
+    let mut options_ended = false;
+    while let Some(arg) = args.next() {
+        if arg == "--" && !options_ended {
+            options_ended = true;
+            continue;
+        }
+        // Expressions can start with a negation, like `-(x + y)`
+        let is_option = !options_ended
+            && arg.starts_with('-')
+            && arg != "-"
+            && (command != "simplify" || looks_like_option(&arg));
+        if !is_option {
+            if input.is_some() {
+                return Err(CliError::Usage(format!("Unexpected argument {arg:?}")));
+            }
+            input = Some(match arg.as_str() {
+                "-" => Input::Stdin,
+                _ if command == "simplify" => Input::Argument(arg),
+                _ => Input::File(arg.into()),
+            });
+            continue;
+        }
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(Command::Help),
+            "-f" | "--format" => {
+                options.format = match value(&mut args, &arg)?.as_str() {
+                    "text" => Format::Text,
+                    "latex" => Format::Latex,
+                    "typst" => Format::Typst,
+                    "html" => Format::Html,
+                    "json" => Format::Json,
+                    format => return Err(CliError::Usage(format!("Unknown format {format:?}"))),
+                }
+            }
+            "-w" | "--width" => {
+                let width = value(&mut args, &arg)?;
+                options.line_width = Some(
+                    width
+                        .parse()
+                        .map_err(|_| CliError::Usage(format!("Invalid width {width:?}")))?,
+                );
+            }
+            "--file" if input.is_some() => {
+                return Err(CliError::Usage(
+                    "Give either an expression or --file".to_owned(),
+                ))
+            }
+            "--file" => input = Some(Input::File(value(&mut args, &arg)?.into())),
+            "-o" | "--out" => out = Some(PathBuf::from(value(&mut args, &arg)?)),
+            flag => return Err(CliError::Usage(format!("Unknown option {flag}"))),
+        }
+    }
+    let input = input.unwrap_or(Input::Stdin);
+    if out.is_some() && command != "batch" {
+        return Err(CliError::Usage("--out is only for batch".to_owned()));
+    }
+    match command.as_str() {
+        "simplify" => Ok(Command::Simplify(input, options)),
+        "render" => Ok(Command::Render(input, options)),
+        "verify" => Ok(Command::Verify(input)),
+        "batch" => match out {
+            Some(out) => Ok(Command::Batch(input, out, options)),
+            None => Err(CliError::Usage("batch needs --out DIR".to_owned())),
+        },
+        "repl" if input == Input::Stdin => Ok(Command::Repl),
+        "repl" => Err(CliError::Usage("repl reads from stdin".to_owned())),
+        "-h" | "--help" | "help" => Ok(Command::Help),
+        command => Err(CliError::Usage(format!("Unknown command {command:?}"))),
+    }
+}
+
+fn print_steps(steps: &Step, options: &Options) -> String {
+    print_steps_with(steps, options, PrintTarget::LaTex)
+}
+
+/// Like [`print_steps`], with the math in HTML documents printed for `html_target`.
+fn print_steps_with(steps: &Step, options: &Options, html_target: PrintTarget) -> String {
+    let print_opts = |target| {
+        StepPrintOpts::uniform(PrintOpts {
+            line_width: options.line_width,
+            ..PrintOpts::default_for(target)
+        })
+    };
+    match options.format {
+        Format::Text => steps.math_print(&print_opts(PrintTarget::MathPrint)),
+        Format::Latex => steps.latex_print(&print_opts(PrintTarget::LaTex)),
+        Format::Typst => steps.typst_document(&print_opts(PrintTarget::Typst)),
+        Format::Json => step_to_json(steps),
+        Format::Html => html_document(
+            steps,
+            &HtmlOptions {
+                print_opts: print_opts(html_target),
+                ..HtmlOptions::new(html_target)
+            },
+        ),
+    }
+}
+
+/// Runs the command, returning what to print.
+fn run(command: Command) -> Result<String, CliError> {
+    match command {
+        Command::Simplify(input, options) => {
+            let text = input.read()?;
+            let expression = parse(text.trim()).map_err(|error| parse_error(text.trim(), error))?;
+            Ok(print_steps(&simplify(&expression), &options))
+        }
+        Command::Render(input, options) => {
+            Ok(print_steps(&step_from_json(&input.read()?)?, &options))
+        }
+        Command::Verify(input) => {
+            verify(&step_from_json(&input.read()?)?).map_err(CliError::Unsound)?;
+            Ok("Every step is sound".to_owned())
+        }
+        Command::Batch(input, out, options) => batch::run_batch(&input.read()?, &out, &options),
+        Command::Repl => {
+            repl::run(io::stdin().lock(), io::stdout())?;
+            Ok(String::new())
+        }
+        Command::Help => Ok(USAGE.trim_end().to_owned()),
+    }
+}
+
+fn main() -> ExitCode {
+    match parse_args(std::env::args().skip(1)).and_then(run) {
+        Ok(output) => {
+            if !output.is_empty() {
+                println!("{output}");
+            }
+            ExitCode::SUCCESS
+        }
+        Err(error) => {
+            eprintln!("{error}");
+            ExitCode::from(error.exit_code())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &str) -> Result<Command, CliError> {
+        parse_args(args.split(' ').map(str::to_owned))
+    }
+
+    #[test]
+    fn test_parse_args() {
+        assert_eq!(
+            args("simplify x --format json -w 40").unwrap(),
+            Command::Simplify(
+                Input::Argument("x".to_owned()),
+                Options {
+                    format: Format::Json,
+                    line_width: Some(40),
+                }
+            )
+        );
+        assert_eq!(
+            args("render --format html").unwrap(),
+            Command::Render(
+                Input::Stdin,
+                Options {
+                    format: Format::Html,
+                    line_width: None,
+                }
+            )
+        );
+        assert_eq!(
+            args("verify steps.json").unwrap(),
+            Command::Verify(Input::File("steps.json".into()))
+        );
+        assert_eq!(args("repl").unwrap(), Command::Repl);
+        assert_eq!(
+            parse_args(["simplify", "-(x + y)", "-w", "40"].map(str::to_owned)).unwrap(),
+            Command::Simplify(
+                Input::Argument("-(x + y)".to_owned()),
+                Options {
+                    format: Format::Text,
+                    line_width: Some(40),
+                }
+            )
+        );
+        assert_eq!(
+            args("simplify -f json -- -x").unwrap(),
+            Command::Simplify(
+                Input::Argument("-x".to_owned()),
+                Options {
+                    format: Format::Json,
+                    line_width: None,
+                }
+            )
+        );
+        assert_eq!(args("simplify -x").unwrap_err().exit_code(), 2);
+        assert_eq!(args("verify -(x)").unwrap_err().exit_code(), 2);
+        assert_eq!(
+            args("batch problems.txt -o out -f latex").unwrap(),
+            Command::Batch(
+                Input::File("problems.txt".into()),
+                "out".into(),
+                Options {
+                    format: Format::Latex,
+                    line_width: None,
+                }
+            )
+        );
+        assert_eq!(args("batch problems.txt").unwrap_err().exit_code(), 2);
+        assert_eq!(args("simplify x -o out").unwrap_err().exit_code(), 2);
+        assert_eq!(args("simplify --file a.txt b").unwrap_err().exit_code(), 2);
+        assert_eq!(args("simplify x -f svg").unwrap_err().exit_code(), 2);
+        assert_eq!(args("expand x").unwrap_err().exit_code(), 2);
+    }
+
+    #[test]
+    fn test_run() {
+        let simplify = |input: &str| {
+            run(Command::Simplify(
+                Input::Argument(input.to_owned()),
+                Options {
+                    format: Format::Text,
+                    line_width: None,
+                },
+            ))
+        };
+        insta::assert_snapshot!(simplify("(x * y) * z").unwrap(), @r###"
+        Simplify excess parentheses
+          Remove excess parentheses around product
+            (x * y) * z
+             ^^^^^
+            x * y * z
+            ~   ~
+          x * y * z
+        "###);
+
+        let error = simplify("x * (y + ) * z").unwrap_err();
+        assert_eq!(error.exit_code(), 3);
+        insta::assert_display_snapshot!(error, @r###"
+        Unexpected ')' at 9
+          x * (y + ) * z
+                   ^
+        "###);
+    }
+
+    #[test]
+    fn test_render_unlabeled() {
+        // Labels are optional in saved steps
+        let unlabeled = r#"{
+            "substeps": [],
+            "result": { "expression": { "kind": "constant", "id": 1, "name": "x" } }
+        }"#;
+        let html = run(Command::Render(
+            Input::Argument(unlabeled.to_owned()),
+            Options {
+                format: Format::Html,
+                line_width: None,
+            },
+        ))
+        .unwrap();
+        assert!(html.contains(r#"<div class="substeps">"#));
+        assert!(!html.contains("<details>"));
+    }
+}
@@ -0,0 +1,340 @@
+//! An interactive session for stepping through how expressions simplify.
+
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+
+use math_thing::{
+    find_rule, parse, simplify, AsExpression, Expression, Negation, Product, Step, Sum, RULES,
+};
+
+const HELP: &str = "\
+Type an expression to simplify it, like `(x * y) * z`.
+
+Commands:
+  let NAME = EXPRESSION  Names an expression, to use in later expressions
+  steps                  Shows the steps of the last simplification
+  expand N               Shows every detail of step N
+  next, prev             Shows the step after or before the last one shown
+  subterms               Lists the parts of the current expression, with their paths
+  rules                  Lists the rules that can be applied
+  apply RULE [PATH]      Applies a rule to the part of the current expression at PATH,
+                         or to the whole expression
+  history                Lists the expressions so far
+  vars                   Lists the named expressions
+  help                   Prints this message
+  quit                   Ends the session";
+
+#[derive(Default)]
+pub(crate) struct Repl {
+    variables: HashMap<String, Expression>,
+    /// Every expression entered, or produced by `apply`. The last one is the current expression.
+    history: Vec<Expression>,
+    steps: Option<Step>,
+    /// The step last shown by `expand`, `next` or `prev`, counting from 1.
+    shown: usize,
+}
+
+/// Replaces constants named like a variable with the variable's expression.
+/// Each use is rebuilt as separate nodes, so highlighting one doesn't highlight the others.
+fn substitute(expr: &Expression, variables: &HashMap<String, Expression>) -> Expression {
+    let terms = |terms: &[Expression]| -> Vec<Expression> {
+        terms
+            .iter()
+            .map(|term| substitute(term, variables))
+            .collect()
+    };
+    match expr {
+        Expression::Constant(constant) => variables
+            .get(constant.name())
+            .map_or_else(|| expr.clone(), |value| substitute(value, &HashMap::new())),
+        Expression::Sum(sum) => Sum::new(terms(sum.terms())).expr(),
+        Expression::Product(product) => Product::new(terms(product.terms())).expr(),
+        Expression::Negation(negation) => {
+            Negation::new(substitute(negation.inner(), variables)).expr()
+        }
+    }
+}
+
+/// Lists `expr` and everything inside it, each with the path that picks it out.
+fn list_subterms(expr: &Expression, path: &mut Vec<usize>, lines: &mut Vec<(String, String)>) {
+    let name = if path.is_empty() {
+        ".".to_owned()
+    } else {
+        path.iter()
+            .map(usize::to_string)
+            .collect::<Vec<_>>()
+            .join(".")
+    };
+    lines.push((name, expr.to_string()));
+    for index in 0.. {
+        path.push(index);
+        let Some(child) = expr.subexpression(&[index]) else {
+            path.pop();
+            break;
+        };
+        list_subterms(child, path, lines);
+        path.pop();
+    }
+}
+
+fn parse_path(path: &str) -> Result<Vec<usize>, String> {
+    if path == "." {
+        return Ok(vec![]);
+    }
+    path.split('.')
+        .map(|index| {
+            index
+                .parse()
+                .map_err(|_| format!("Invalid path {path:?}, expected something like 0.1"))
+        })
+        .collect()
+}
+
+impl Repl {
+    fn expression(&self, input: &str) -> Result<Expression, String> {
+        let expression = parse(input).map_err(|error| error.to_string())?;
+        Ok(substitute(&expression, &self.variables))
+    }
+
+    fn current(&self) -> Result<&Expression, String> {
+        self.history
+            .last()
+            .ok_or_else(|| "Enter an expression first".to_owned())
+    }
+
+    fn steps(&self) -> Result<&Step, String> {
+        self.steps
+            .as_ref()
+            .ok_or_else(|| "Enter an expression first".to_owned())
+    }
+
+    /// The steps with only the labels of their top-level substeps.
+    fn collapsed(&self) -> Result<String, String> {
+        let steps = self.steps()?;
+        if steps.substeps.is_empty() {
+            return Ok(format!("Already simplified: {}", steps.result));
+        }
+        let mut lines = vec![steps.label.clone().unwrap_or_default()];
+        for (i, step) in steps.substeps.iter().enumerate() {
+            let label = step.label.as_deref().unwrap_or("Step");
+            lines.push(format!("  {}. {label}", i + 1));
+        }
+        lines.push(format!("  {}", steps.result));
+        Ok(lines.join("\n"))
+    }
+
+    /// Shows every detail of a top-level step, counting from 1.
+    fn show(&mut self, number: usize) -> Result<String, String> {
+        let count = self.steps()?.substeps.len();
+        if number == 0 || number > count {
+            return Err(format!("There are steps 1 to {count}"));
+        }
+        self.shown = number;
+        Ok(format!(
+            "Step {number} of {count}: {}",
+            self.steps()?.substeps[number - 1]
+        ))
+    }
+
+    fn apply(&mut self, rule: &str, path: Option<&str>) -> Result<String, String> {
+        let rule = find_rule(rule).ok_or_else(|| format!("No rule is called {rule:?}"))?;
+        let path = path.map_or(Ok(vec![]), parse_path)?;
+        let current = self.current()?;
+        if current.subexpression(&path).is_none() {
+            return Err("Nothing is at that path, see `subterms`".to_owned());
+        }
+        let step = rule
+            .apply_at(current, &path)
+            .ok_or_else(|| format!("{} doesn't apply there", rule.name))?;
+        self.history.push(step.result.clone());
+        Ok(step.to_string())
+    }
+
+    /// Runs one line of input, returning what to print.
+    pub(crate) fn eval(&mut self, line: &str) -> Result<String, String> {
+        let (command, rest) = line.split_once(' ').unwrap_or((line, ""));
+        let rest = rest.trim();
+        match command {
+            "" => Ok(String::new()),
+            "help" => Ok(HELP.to_owned()),
+            "let" => {
+                let (name, expression) = rest
+                    .split_once('=')
+                    .ok_or_else(|| "Expected `let NAME = EXPRESSION`".to_owned())?;
+                let Ok(Expression::Constant(name)) = parse(name.trim()) else {
+                    return Err(format!("{:?} can't be a name", name.trim()));
+                };
+                let expression = self.expression(expression.trim())?;
+                let output = format!("{} = {expression}", name.expr());
+                self.variables.insert(name.name().to_owned(), expression);
+                Ok(output)
+            }
+            "steps" => self.collapsed(),
+            "expand" => {
+                let number = rest
+                    .parse()
+                    .map_err(|_| "Expected `expand N`, like `expand 1`".to_owned())?;
+                self.show(number)
+            }
+            "next" => self.show(self.shown + 1),
+            "prev" => self.show(self.shown.saturating_sub(1)),
+            "subterms" => {
+                let mut lines = vec![];
+                list_subterms(self.current()?, &mut vec![], &mut lines);
+                let width = lines.iter().map(|(path, _)| path.len()).max().unwrap_or(0);
+                Ok(lines
+                    .iter()
+                    .map(|(path, expression)| format!("{path:width$}  {expression}"))
+                    .collect::<Vec<_>>()
+                    .join("\n"))
+            }
+            "rules" => Ok(RULES
+                .iter()
+                .map(|rule| format!("{}: {}", rule.name, rule.label))
+                .collect::<Vec<_>>()
+                .join("\n")),
+            "apply" => {
+                let mut args = rest.split_whitespace();
+                let rule = args
+                    .next()
+                    .ok_or_else(|| "Expected `apply RULE [PATH]`".to_owned())?;
+                self.apply(rule, args.next())
+            }
+            "history" => Ok(self
+                .history
+                .iter()
+                .enumerate()
+                .map(|(i, expression)| format!("{}. {expression}", i + 1))
+                .collect::<Vec<_>>()
+                .join("\n")),
+            "vars" => {
+                let mut variables: Vec<_> = self.variables.iter().collect();
+                variables.sort_by_key(|(name, _)| name.as_str());
+                Ok(variables
+                    .iter()
+                    .map(|(name, expression)| format!("{name} = {expression}"))
+                    .collect::<Vec<_>>()
+                    .join("\n"))
+            }
+            _ => {
+                let expression = self.expression(line)?;
+                self.steps = Some(simplify(&expression));
+                self.shown = 0;
+                self.history.push(expression);
+                self.collapsed()
+            }
+        }
+    }
+}
+
+/// Reads commands from `input` until it ends or the user quits.
+pub(crate) fn run(input: impl BufRead, mut output: impl Write) -> io::Result<()> {
+    let mut repl = Repl::default();
+    write!(output, "> ")?;
+    output.flush()?;
+    for line in input.lines() {
+        let line = line?;
+        let line = line.trim();
+        if matches!(line, "quit" | "exit") {
+            break;
+        }
+        match repl.eval(line) {
+            Ok(text) if text.is_empty() => {}
+            Ok(text) => writeln!(output, "{text}")?,
+            Err(message) => writeln!(output, "Error: {message}")?,
+        }
+        write!(output, "> ")?;
+        output.flush()?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_session() {
+        let mut repl = Repl::default();
+        let mut eval = |line| match repl.eval(line) {
+            Ok(output) => output,
+            Err(message) => format!("Error: {message}"),
+        };
+        insta::assert_snapshot!(eval("let f = x * y"), @"f = x * y");
+        insta::assert_snapshot!(eval("(f * z) + ((x + y) + f)"), @r###"
+        Simplify excess parentheses
+          1. Remove excess parentheses around product
+          2. Remove excess parentheses around sum
+          3. Remove excess parentheses around sum
+          x * y * z + x + y + x * y
+        "###);
+        insta::assert_snapshot!(eval("next"), @r###"
+        Step 1 of 3: Remove excess parentheses around product
+          (x * y) * z + ((x + y) + x * y)
+           ^^^^^
+          x * y * z + ((x + y) + x * y)
+          ~   ~
+        "###);
+        insta::assert_snapshot!(eval("next"), @r###"
+        Step 2 of 3: Remove excess parentheses around sum
+          x * y * z + ((x + y) + x * y)
+                        ^^^^^
+          x * y * z + (x + y + x * y)
+                       ~   ~
+        "###);
+        insta::assert_snapshot!(eval("expand 3"), @r###"
+        Step 3 of 3: Remove excess parentheses around sum
+          x * y * z + (x + y + x * y)
+                       ^^^^^^^^^^^^^
+          x * y * z + x + y + x * y
+                      ~   ~   ~~~~~
+        "###);
+        insta::assert_snapshot!(eval("subterms"), @r###"
+        .      (x * y) * z + ((x + y) + x * y)
+        0      (x * y) * z
+        0.0    x * y
+        0.0.0  x
+        0.0.1  y
+        0.1    z
+        1      (x + y) + x * y
+        1.0    x + y
+        1.0.0  x
+        1.0.1  y
+        1.1    x * y
+        1.1.0  x
+        1.1.1  y
+        "###);
+        insta::assert_snapshot!(eval("apply flatten-sum 1"), @r###"
+        Remove excess parentheses around sum
+          (x * y) * z + ((x + y) + x * y)
+                          ^^^^^
+          (x * y) * z + (x + y + x * y)
+                         ~   ~
+        "###);
+        insta::assert_snapshot!(eval("apply flatten-sum 1"), @"Error: flatten-sum doesn't apply there");
+        insta::assert_snapshot!(eval("apply flatten-product 0"), @r###"
+        Remove excess parentheses around product
+          (x * y) * z + (x + y + x * y)
+           ^^^^^
+          x * y * z + (x + y + x * y)
+          ~   ~
+        "###);
+        insta::assert_snapshot!(eval("history"), @r###"
+        1. (x * y) * z + ((x + y) + x * y)
+        2. (x * y) * z + (x + y + x * y)
+        3. x * y * z + (x + y + x * y)
+        "###);
+        insta::assert_snapshot!(eval("x * y"), @"Already simplified: x * y");
+    }
+
+    #[test]
+    fn test_run() {
+        let mut output = vec![];
+        run("rules\nquit\nx".as_bytes(), &mut output).unwrap();
+        insta::assert_snapshot!(String::from_utf8(output).unwrap(), @r###"
+        > flatten-product: Remove excess parentheses around product
+        flatten-sum: Remove excess parentheses around sum
+        >
+        "###);
+    }
+}
@@ -0,0 +1,381 @@
+//! An HTTP API for generating steps, for integrations that can't link the crate.
+//!
+//! - `GET /rules` lists the rules a pipeline can use, as `[{ "name", "label" }]`.
+//! - `POST /simplify` takes `{ "expr": "(x * y) * z", "pipeline": ["flatten-product"] }`
+//!   and returns `{ "steps", "html" }`, where `steps` matches `schema/step.schema.json`
+//!   and `html` shows the steps with the expressions as MathML.
+//!   Without a `pipeline`, every rule is used.
+//!
+//! Errors are returned as `{ "error" }` with a 4xx status.
+
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::process::ExitCode;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use math_thing::{
+    find_rule, parse, simplify, simplify_with, step_to_json, PrintOpts, PrintTarget, StepPrintOpts,
+    RULES,
+};
+
+const USAGE: &str = "Usage: math-thing-server [ADDRESS]
+
+Serves the HTTP API on ADDRESS, 127.0.0.1:8080 by default.";
+
+/// Requests with a bigger request line and headers are refused.
+const MAX_HEADER_LENGTH: u64 = 8 << 10;
+
+/// Requests with bigger bodies are refused.
+const MAX_BODY_LENGTH: usize = 1 << 20;
+
+/// How long a client can take to send its whole request, or to read each part of the response,
+/// before its connection is dropped.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Reads from a connection until a deadline, after which reads fail with
+/// [`io::ErrorKind::TimedOut`].
+struct Deadline {
+    stream: TcpStream,
+    deadline: Instant,
+}
+
+impl Read for Deadline {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let remaining = self.deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                "The request took too long to send",
+            ));
+        }
+        self.stream.set_read_timeout(Some(remaining))?;
+        self.stream.read(buf)
+    }
+}
+
+struct Request {
+    method: String,
+    path: String,
+    body: Vec<u8>,
+}
+
+struct Response {
+    status: u16,
+    body: String,
+}
+
+#[derive(Deserialize)]
+struct SimplifyRequest {
+    expr: String,
+    #[serde(default)]
+    pipeline: Option<Vec<String>>,
+}
+
+#[derive(Serialize)]
+struct SimplifyResponse {
+    steps: serde_json::Value,
+    html: String,
+}
+
+#[derive(Serialize)]
+struct RuleJson {
+    name: &'static str,
+    label: &'static str,
+}
+
+#[derive(Serialize)]
+struct ErrorJson {
+    error: String,
+}
+
+impl Response {
+    fn json(status: u16, body: &impl Serialize) -> Self {
+        Self {
+            status,
+            body: serde_json::to_string(body).unwrap(),
+        }
+    }
+
+    fn error(status: u16, error: impl ToString) -> Self {
+        Self::json(
+            status,
+            &ErrorJson {
+                error: error.to_string(),
+            },
+        )
+    }
+
+    fn write_to(&self, stream: &mut impl Write) -> io::Result<()> {
+        let reason = match self.status {
+            200 => "OK",
+            400 => "Bad Request",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            413 => "Content Too Large",
+            431 => "Request Header Fields Too Large",
+            _ => "",
+        };
+        write!(
+            stream,
+            "HTTP/1.1 {} {reason}\r\n\
+             Content-Type: application/json\r\n\
+             Content-Length: {}\r\n\
+             Connection: close\r\n\
+             \r\n\
+             {}",
+            self.status,
+            self.body.len(),
+            self.body
+        )?;
+        stream.flush()
+    }
+}
+
+/// Reads a request, or returns the response to send if it can't be handled.
+fn read_request(stream: &mut impl BufRead) -> io::Result<Result<Request, Response>> {
+    let mut head = stream.take(MAX_HEADER_LENGTH);
+    let mut line = String::new();
+    let too_large = |head: &io::Take<_>, line: &str| head.limit() == 0 && !line.ends_with('\n');
+    head.read_line(&mut line)?;
+    if too_large(&head, &line) {
+        return Ok(Err(Response::error(431, "The headers are too large")));
+    }
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(path)) = (parts.next(), parts.next()) else {
+        return Ok(Err(Response::error(400, "Invalid request line")));
+    };
+    let (method, path) = (method.to_owned(), path.to_owned());
+
+    let mut content_length = 0;
+    loop {
+        line.clear();
+        if head.read_line(&mut line)? == 0 || line.trim().is_empty() {
+            break;
+        }
+        if too_large(&head, &line) {
+            return Ok(Err(Response::error(431, "The headers are too large")));
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("content-length") {
+                let Ok(length) = value.trim().parse() else {
+                    return Ok(Err(Response::error(400, "Invalid Content-Length")));
+                };
+                content_length = length;
+            }
+        }
+    }
+    if content_length > MAX_BODY_LENGTH {
+        return Ok(Err(Response::error(413, "The body is too large")));
+    }
+    let mut body = vec![0; content_length];
+    stream.read_exact(&mut body)?;
+    Ok(Ok(Request { method, path, body }))
+}
+
+fn simplify_request(body: &[u8]) -> Response {
+    let request: SimplifyRequest = match serde_json::from_slice(body) {
+        Ok(request) => request,
+        Err(error) => return Response::error(400, format!("Invalid request: {error}")),
+    };
+    let expression = match parse(&request.expr) {
+        Ok(expression) => expression,
+        Err(error) => return Response::error(400, error),
+    };
+    let steps = match request.pipeline {
+        None => simplify(&expression),
+        Some(pipeline) => {
+            let mut rules = vec![];
+            for name in &pipeline {
+                match find_rule(name) {
+                    Some(rule) => rules.push(rule),
+                    None => return Response::error(400, format!("Unknown rule {name:?}")),
+                }
+            }
+            simplify_with(&expression, &rules)
+        }
+    };
+    let print_opts = StepPrintOpts::uniform(PrintOpts::default_for(PrintTarget::MathML));
+    Response::json(
+        200,
+        &SimplifyResponse {
+            steps: serde_json::from_str(&step_to_json(&steps)).unwrap(),
+            html: steps.html_print(PrintTarget::MathML, &print_opts),
+        },
+    )
+}
+
+fn handle(request: &Request) -> Response {
+    match (request.method.as_str(), request.path.as_str()) {
+        ("GET", "/rules") => {
+            let rules: Vec<_> = RULES
+                .iter()
+                .map(|rule| RuleJson {
+                    name: rule.name,
+                    label: rule.label,
+                })
+                .collect();
+            Response::json(200, &rules)
+        }
+        ("POST", "/simplify") => simplify_request(&request.body),
+        (_, "/rules" | "/simplify") => Response::error(405, "Method not allowed"),
+        (_, path) => Response::error(404, format!("Nothing is at {path}")),
+    }
+}
+
+fn serve_connection(stream: TcpStream) -> io::Result<()> {
+    stream.set_write_timeout(Some(TIMEOUT))?;
+    let mut reader = BufReader::new(Deadline {
+        stream: stream.try_clone()?,
+        deadline: Instant::now() + TIMEOUT,
+    });
+    let response = match read_request(&mut reader)? {
+        Ok(request) => handle(&request),
+        Err(response) => response,
+    };
+    response.write_to(&mut &stream)
+}
+
+fn serve(listener: TcpListener) {
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => {
+                std::thread::spawn(move || {
+                    if let Err(error) = serve_connection(stream) {
+                        eprintln!("{error}");
+                    }
+                });
+            }
+            Err(error) => eprintln!("{error}"),
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let address = match std::env::args().nth(1) {
+        Some(arg) if arg == "-h" || arg == "--help" => {
+            println!("{USAGE}");
+            return ExitCode::SUCCESS;
+        }
+        Some(address) => address,
+        None => "127.0.0.1:8080".to_owned(),
+    };
+    match TcpListener::bind(&address) {
+        Ok(listener) => {
+            eprintln!("Listening on http://{address}");
+            serve(listener);
+            ExitCode::SUCCESS
+        }
+        Err(error) => {
+            eprintln!("Couldn't listen on {address}: {error}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Sends a request to a server on localhost, returning the status line and body.
+    fn request(method: &str, path: &str, body: &str) -> (String, String) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        std::thread::spawn(move || serve(listener));
+
+        let mut stream = TcpStream::connect(address).unwrap();
+        write!(
+            stream,
+            "{method} {path} HTTP/1.1\r\nHost: {address}\r\nContent-Length: {}\r\n\r\n{body}",
+            body.len()
+        )
+        .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        (head.lines().next().unwrap().to_owned(), body.to_owned())
+    }
+
+    #[test]
+    fn test_rules() {
+        let (status, body) = request("GET", "/rules", "");
+        assert_eq!(status, "HTTP/1.1 200 OK");
+        assert_eq!(
+            body,
+            r#"[{"name":"flatten-product","label":"Remove excess parentheses around product"},{"name":"flatten-sum","label":"Remove excess parentheses around sum"}]"#
+        );
+    }
+
+    #[test]
+    fn test_simplify() {
+        let (status, body) = request(
+            "POST",
+            "/simplify",
+            r#"{ "expr": "((x * y) * z) + ((x + y) + z)", "pipeline": ["flatten-product"] }"#,
+        );
+        assert_eq!(status, "HTTP/1.1 200 OK");
+        let response: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(
+            response["steps"]["result"]["mathPrint"].as_str(),
+            Some("x * y * z + ((x + y) + z)")
+        );
+        assert!(response["html"].as_str().unwrap().contains("<math>"));
+
+        let simplify = |body| request("POST", "/simplify", body);
+        assert_eq!(
+            simplify(r#"{ "expr": "x * (y + ) * z" }"#),
+            (
+                "HTTP/1.1 400 Bad Request".to_owned(),
+                r#"{"error":"Unexpected ')' at 9"}"#.to_owned()
+            )
+        );
+        assert_eq!(
+            simplify(r#"{ "expr": "x", "pipeline": ["expand"] }"#),
+            (
+                "HTTP/1.1 400 Bad Request".to_owned(),
+                r#"{"error":"Unknown rule \"expand\""}"#.to_owned()
+            )
+        );
+        assert_eq!(simplify(r#"{ "exp": "x" }"#).0, "HTTP/1.1 400 Bad Request");
+        assert_eq!(
+            request("GET", "/simplify", "").0,
+            "HTTP/1.1 405 Method Not Allowed"
+        );
+        assert_eq!(request("GET", "/", "").0, "HTTP/1.1 404 Not Found");
+    }
+
+    #[test]
+    fn test_limits() {
+        let status = |request: String| match read_request(&mut request.as_bytes()).unwrap() {
+            Ok(_) => 200,
+            Err(response) => response.status,
+        };
+        let header = |length| format!("X: {}\r\n", "a".repeat(length));
+        let head = |headers: &str| format!("GET /rules HTTP/1.1\r\n{headers}\r\n");
+        assert_eq!(status(head(&header(1000))), 200);
+        assert_eq!(status(head(&header(MAX_HEADER_LENGTH as usize))), 431);
+        assert_eq!(status(head(&header(1000).repeat(10))), 431);
+        assert_eq!(
+            status(format!("GET /{} HTTP/1.1\r\n\r\n", "a".repeat(10_000))),
+            431
+        );
+        assert_eq!(status(head("Content-Length: 2000000\r\n")), 413);
+
+        // The deadline is for the whole request, not each read
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let _client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (stream, _) = listener.accept().unwrap();
+        let mut reader = Deadline {
+            stream,
+            deadline: Instant::now() + Duration::from_millis(50),
+        };
+        let error = reader.read(&mut [0; 16]).unwrap_err();
+        assert!(matches!(
+            error.kind(),
+            io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock
+        ));
+        let error = reader.read(&mut [0; 16]).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::TimedOut);
+    }
+}
@@ -2,24 +2,27 @@ use std::rc::Rc;
 
 use crate::{
     annotated_expression::Annotation,
+    evaluate::{EvalError, Evaluable, Number},
     expression::{gen_id, Expression, ExpressionId},
-    token_stream::TokenStream,
+    token_stream::{escape_html, TokenStream},
     tokens,
     traverse::Traversable,
-    PrintOpts, PrintTarget, Printable,
+    GreekLetters, PrintOpts, PrintTarget, Printable,
 };
 
 pub(crate) struct ConstantInfo {
     pub(crate) name: String,
 }
 
+/// A named variable or constant, like `x` or `π`.
+/// Every use of the same `Constant` is the same node, with the same id.
 #[derive(Clone)]
-pub(crate) struct Constant {
+pub struct Constant {
     info: Rc<ConstantInfo>,
     id: ExpressionId,
 }
 
-fn latex_to_unicode(latex: &str) -> Option<&'static str> {
+pub(crate) fn latex_to_unicode(latex: &str) -> Option<&'static str> {
     match latex {
         r"\pi" => Some("π"),
         r"\rho" => Some("ρ"),
@@ -27,7 +30,19 @@ fn latex_to_unicode(latex: &str) -> Option<&'static str> {
     }
 }
 
-fn unicode_to_latex(unicode: &str) -> &str {
+/// Typst has its own names for symbols (`pi` instead of `\pi`),
+/// and reads multi-letter names as variables unless they are quoted.
+fn latex_to_typst(latex: &str) -> String {
+    if let Some(symbol) = latex.strip_prefix('\\') {
+        symbol.to_owned()
+    } else if latex.chars().count() == 1 {
+        latex.to_owned()
+    } else {
+        format!("\"{latex}\"")
+    }
+}
+
+pub(crate) fn unicode_to_latex(unicode: &str) -> &str {
     match unicode {
         "π" => r"\pi",
         "ρ" => r"\rho",
@@ -36,43 +51,78 @@ fn unicode_to_latex(unicode: &str) -> &str {
 }
 
 impl Constant {
+    /// Panics if `name` is a LaTeX command that isn't supported, see [`Constant::try_new`].
     pub fn new(name: &str) -> Self {
-        if name.starts_with('\\') {
-            // Assume latex character, look up
-            if latex_to_unicode(name).is_none() {
-                panic!("Unrecognized LaTeX code {name}");
-            }
+        Self::try_new(name).unwrap_or_else(|| panic!("Unrecognized LaTeX code {name}"))
+    }
+
+    /// A constant named `name`, or `None` if it's a LaTeX command that isn't supported.
+    pub fn try_new(name: &str) -> Option<Self> {
+        if name.starts_with('\\') && latex_to_unicode(name).is_none() {
+            return None;
         }
         let constant_info = ConstantInfo {
             name: unicode_to_latex(name).to_owned(),
         };
-        Constant {
+        Some(Constant {
             info: Rc::new(constant_info),
             id: gen_id(),
+        })
+    }
+    /// The same constant as a separate node, which annotations can highlight on its own.
+    pub(crate) fn with_new_id(&self) -> Self {
+        Constant {
+            info: self.info.clone(),
+            id: gen_id(),
         }
     }
     #[inline]
-    pub(crate) fn id(&self) -> ExpressionId {
+    pub fn id(&self) -> ExpressionId {
         self.id
     }
+    /// The name of the constant, as LaTeX.
+    #[inline]
+    pub fn name(&self) -> &str {
+        &self.info.name
+    }
 }
 
 impl Printable for Constant {
     #[inline]
-    fn print(&self, print_opts: &PrintOpts, _annotations: &[Annotation]) -> TokenStream {
+    fn print(
+        &self,
+        target: PrintTarget,
+        print_opts: &PrintOpts,
+        _annotations: &[Annotation],
+    ) -> TokenStream {
+        let name = match print_opts.greek {
+            GreekLetters::Unicode => latex_to_unicode(&self.info.name).unwrap_or(&self.info.name),
+            GreekLetters::Ascii => &self.info.name,
+        };
         tokens!(std::iter::once(
-            if matches!(print_opts.target, PrintTarget::LaTex) {
-                self.info.name.to_owned()
-            } else {
-                latex_to_unicode(&self.info.name)
-                    .unwrap_or(&self.info.name)
-                    .to_owned()
+            match target {
+                PrintTarget::LaTex => name.to_owned(),
+                PrintTarget::MathPrint => name.trim_start_matches('\\').to_owned(),
+                PrintTarget::MathML => {
+                    format!("<mi>{}</mi>", escape_html(name.trim_start_matches('\\')))
+                }
+                PrintTarget::Typst => latex_to_typst(name),
             }
             .into()
         ))
     }
 }
 
+impl Evaluable for Constant {
+    fn evaluate_with<V, F>(&self, lookup: &F) -> Result<V, EvalError>
+    where
+        V: Number,
+        F: Fn(&str) -> Result<V, EvalError>,
+    {
+        lookup(&self.info.name)
+    }
+}
+
 impl Traversable for Constant {
     fn child_iter<'a>(&'a self) -> Box<dyn Iterator<Item = &'a Expression> + 'a> {
         Box::new(std::iter::empty())
@@ -0,0 +1,217 @@
+use std::collections::{BTreeMap, HashMap};
+
+use num_bigint::BigInt;
+use num_traits::{One, Signed, ToPrimitive, Zero};
+
+use crate::{
+    constant::Constant,
+    evaluate::Bindings,
+    expression::{AsExpression, Expression},
+    negation::Negation,
+    product::Product,
+    sum::Sum,
+    traverse::Traversable,
+    verify::find_counterexample,
+};
+
+/// Expanding products of sums can blow up quickly,
+/// so canonical forms are only found for polynomials up to this many terms.
+const MAX_TERMS: usize = 1000;
+/// Expressions can't contain numbers, so `3x` is written as `x + x + x`.
+/// Canonical forms are only found when no coefficient is larger than this.
+const MAX_COEFFICIENT: u32 = 100;
+
+/// Whether two expressions are equal, as decided by [`are_equivalent`].
+#[derive(Debug)]
+pub enum Equivalence {
+    /// Both expressions have the same canonical form.
+    Proven,
+    /// Both expressions evaluated to the same values everywhere they were tested.
+    ProbablyEqual,
+    /// Both expressions evaluate to different values with these variable values.
+    NotEqual(Bindings),
+}
+
+/// Decides whether two expressions are equal for every value of their variables.
+pub fn are_equivalent(a: &Expression, b: &Expression) -> Equivalence {
+    if a.structurally_eq(b) {
+        return Equivalence::Proven;
+    }
+    if let (Some(a), Some(b)) = (canonical_form(a), canonical_form(b)) {
+        if a.structurally_eq(&b) {
+            return Equivalence::Proven;
+        }
+    }
+    match find_counterexample(a, b) {
+        Some(counterexample) => Equivalence::NotEqual(counterexample),
+        None => Equivalence::ProbablyEqual,
+    }
+}
+
+/// Each monomial is the sorted list of constant names multiplied together, mapped to its coefficient.
+type Polynomial = BTreeMap<Vec<String>, BigInt>;
+
+fn polynomial(expr: &Expression) -> Option<Polynomial> {
+    let polynomial = match expr {
+        Expression::Constant(constant) => {
+            Polynomial::from([(vec![constant.name().to_owned()], BigInt::one())])
+        }
+        Expression::Negation(neg) => polynomial(neg.inner())?
+            .into_iter()
+            .map(|(monomial, coefficient)| (monomial, -coefficient))
+            .collect(),
+        Expression::Sum(sum) => {
+            let mut result = Polynomial::new();
+            for term in sum.terms() {
+                for (monomial, coefficient) in polynomial(term)? {
+                    *result.entry(monomial).or_default() += coefficient;
+                }
+            }
+            result
+        }
+        Expression::Product(product) => {
+            let mut result = Polynomial::from([(vec![], BigInt::one())]);
+            for term in product.terms() {
+                let term = polynomial(term)?;
+                let mut next = Polynomial::new();
+                for (a_monomial, a_coefficient) in &result {
+                    for (b_monomial, b_coefficient) in &term {
+                        let mut monomial = a_monomial.clone();
+                        monomial.extend(b_monomial.iter().cloned());
+                        monomial.sort();
+                        *next.entry(monomial).or_default() += a_coefficient * b_coefficient;
+                    }
+                }
+                if next.len() > MAX_TERMS {
+                    return None;
+                }
+                result = next;
+            }
+            result
+        }
+    };
+    Some(
+        polynomial
+            .into_iter()
+            .filter(|(_, coefficient)| !coefficient.is_zero())
+            .collect(),
+    )
+}
+
+fn collect_constants(expr: &Expression, constants: &mut HashMap<String, Constant>) {
+    if let Expression::Constant(constant) = expr {
+        constants
+            .entry(constant.name().to_owned())
+            .or_insert_with(|| constant.as_ref().clone());
+    }
+    for child in expr.child_iter() {
+        collect_constants(child, constants);
+    }
+}
+
+/// Expands the expression into a sum of products of constants,
+/// with like terms combined and everything sorted by constant name.
+/// Equal polynomials have structurally equal canonical forms.
+///
+/// Returns `None` if the expanded form would be too large.
+pub fn canonical_form(expr: &Expression) -> Option<Expression> {
+    let mut constants = HashMap::new();
+    collect_constants(expr, &mut constants);
+
+    let mut terms = vec![];
+    for (monomial, coefficient) in polynomial(expr)? {
+        let factors: Vec<Expression> = monomial.iter().map(|name| constants[name].expr()).collect();
+        let count = coefficient
+            .abs()
+            .to_u32()
+            .filter(|&n| n <= MAX_COEFFICIENT)?;
+        for _ in 0..count {
+            let term = match factors.as_slice() {
+                [factor] => factor.clone(),
+                _ => Product::new(factors.clone()).expr(),
+            };
+            terms.push(if coefficient.is_negative() {
+                Negation::new(term).expr()
+            } else {
+                term
+            });
+        }
+    }
+    Some(match terms.len() {
+        1 => terms.pop().unwrap(),
+        _ => Sum::new(terms).expr(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::math;
+
+    use super::*;
+
+    #[test]
+    fn test_canonical_form() {
+        let x = Constant::new("x");
+        let y = Constant::new("y");
+        let z = Constant::new("z");
+
+        let exp = math![(x + y) * (x - y)].expr();
+        insta::assert_display_snapshot!(canonical_form(&exp).unwrap(), @"x * x - y * y");
+
+        let exp = math![(z * (y + x)) + (-(x * z)) + (y * x)].expr();
+        insta::assert_display_snapshot!(canonical_form(&exp).unwrap(), @"x * y + y * z");
+
+        let exp = math![(x + x) * (-(y + x))].expr();
+        insta::assert_display_snapshot!(canonical_form(&exp).unwrap(), @"-x * x - x * x - x * y - x * y");
+    }
+
+    #[test]
+    fn test_are_equivalent() {
+        let x = Constant::new("x");
+        let y = Constant::new("y");
+        let z = Constant::new("z");
+        let pi = Constant::new(r"\pi");
+
+        let a = math![(x * y) + z].expr();
+        let b = math![(x * y) + z].expr();
+        assert!(matches!(are_equivalent(&a, &b), Equivalence::Proven));
+
+        let a = math![(x + y) * (x + y)].expr();
+        let b = math![(x * x) + (x * y) + (y * x) + (y * y)].expr();
+        assert!(matches!(are_equivalent(&a, &b), Equivalence::Proven));
+
+        let a = math![(-(x - pi)) * y].expr();
+        let b = math![(pi * y) - (y * x)].expr();
+        assert!(matches!(are_equivalent(&a, &b), Equivalence::Proven));
+
+        let a = math![(x + y) * (x + y)].expr();
+        let b = math![(x * x) + (y * y)].expr();
+        match are_equivalent(&a, &b) {
+            Equivalence::NotEqual(counterexample) => {
+                assert_ne!(
+                    a.evaluate(&counterexample).unwrap(),
+                    b.evaluate(&counterexample).unwrap()
+                );
+            }
+            other => panic!("Expected NotEqual, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_large_expressions_are_checked_numerically() {
+        // (a₀ + ... + a₁₀)(b₀ + ... + b₁₀)(c₀ + ... + c₁₀) expands to 1331 terms
+        let factors: Vec<Expression> = ["a", "b", "c"]
+            .iter()
+            .map(|name| {
+                let terms = (0..11)
+                    .map(|i| Constant::new(&format!("{name}_{i}")).expr())
+                    .collect();
+                Sum::new(terms).expr()
+            })
+            .collect();
+        let a = Product::new(factors.clone()).expr();
+        let b = Product::new(factors.into_iter().rev().collect()).expr();
+        assert!(canonical_form(&a).is_none());
+        assert!(matches!(are_equivalent(&a, &b), Equivalence::ProbablyEqual));
+    }
+}
@@ -0,0 +1,342 @@
+use std::collections::HashMap;
+use std::ops::{Add, Mul, Neg};
+
+use num_bigint::BigInt;
+use num_rational::BigRational;
+use num_traits::{One, Signed, Zero};
+
+use crate::constant::{latex_to_unicode, unicode_to_latex};
+
+/// An exact rational number, used by [`Expression::evaluate_exact`](crate::expression::Expression::evaluate_exact).
+pub type Rational = BigRational;
+
+/// The rational number `numerator / denominator`.
+pub fn rational(numerator: i64, denominator: i64) -> Rational {
+    Rational::new(numerator.into(), denominator.into())
+}
+
+/// Values assigned to constants by name, used when evaluating an expression.
+/// The value type is `f64` by default, or [`Rational`] for exact and decimal evaluation.
+#[derive(Debug, Clone)]
+pub struct Bindings<V = f64> {
+    values: HashMap<String, V>,
+}
+
+impl Bindings<f64> {
+    /// Bindings which only know the values of `π` and `e`.
+    pub fn new() -> Self {
+        Self::empty()
+            .bind(r"\pi", std::f64::consts::PI)
+            .bind("e", std::f64::consts::E)
+    }
+}
+
+impl<V> Bindings<V> {
+    /// Bindings which don't know any values, not even `π` and `e`.
+    pub fn empty() -> Self {
+        Self {
+            values: HashMap::new(),
+        }
+    }
+    /// Names can be given either as LaTeX (`\pi`) or as unicode (`π`).
+    pub fn bind(mut self, name: &str, value: V) -> Self {
+        self.values.insert(unicode_to_latex(name).to_owned(), value);
+        self
+    }
+    #[inline]
+    pub fn get(&self, name: &str) -> Option<&V> {
+        self.values.get(unicode_to_latex(name))
+    }
+    /// Iterates over the bindings in an unspecified order.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &V)> {
+        self.values
+            .iter()
+            .map(|(name, value)| (name.as_str(), value))
+    }
+}
+
+impl Default for Bindings<f64> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Why an expression couldn't be evaluated.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EvalError {
+    UnboundVariable(String),
+    /// A constant like `π` has no exact rational value.
+    NotExact(String),
+}
+
+impl std::fmt::Display for EvalError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EvalError::UnboundVariable(name) => write!(
+                f,
+                "No value was given for {}",
+                latex_to_unicode(name).unwrap_or(name)
+            ),
+            EvalError::NotExact(name) => write!(
+                f,
+                "{} cannot be represented exactly as a rational number",
+                latex_to_unicode(name).unwrap_or(name)
+            ),
+        }
+    }
+}
+
+impl std::error::Error for EvalError {}
+
+/// A number type that expressions can be evaluated to.
+pub(crate) trait Number:
+    Clone + Add<Output = Self> + Mul<Output = Self> + Neg<Output = Self>
+{
+    fn zero() -> Self;
+    fn one() -> Self;
+}
+
+impl Number for f64 {
+    #[inline]
+    fn zero() -> Self {
+        0.0
+    }
+    #[inline]
+    fn one() -> Self {
+        1.0
+    }
+}
+
+impl Number for Rational {
+    #[inline]
+    fn zero() -> Self {
+        Zero::zero()
+    }
+    #[inline]
+    fn one() -> Self {
+        One::one()
+    }
+}
+
+pub(crate) trait Evaluable {
+    /// Evaluates the expression, looking up the value of each constant by its (LaTeX) name.
+    fn evaluate_with<V, F>(&self, lookup: &F) -> Result<V, EvalError>
+    where
+        V: Number,
+        F: Fn(&str) -> Result<V, EvalError>;
+}
+
+pub(crate) fn lookup_f64(bindings: &Bindings) -> impl Fn(&str) -> Result<f64, EvalError> + '_ {
+    |name| {
+        bindings
+            .get(name)
+            .copied()
+            .ok_or_else(|| EvalError::UnboundVariable(name.to_owned()))
+    }
+}
+
+pub(crate) fn lookup_exact(
+    bindings: &Bindings<Rational>,
+) -> impl Fn(&str) -> Result<Rational, EvalError> + '_ {
+    |name| match bindings.get(name) {
+        Some(value) => Ok(value.clone()),
+        None if matches!(name, r"\pi" | "e") => Err(EvalError::NotExact(name.to_owned())),
+        None => Err(EvalError::UnboundVariable(name.to_owned())),
+    }
+}
+
+/// Extra digits carried through the calculation so rounding errors
+/// in `π` and `e` don't reach the requested digits.
+const GUARD_DIGITS: usize = 20;
+
+pub(crate) fn lookup_decimal(
+    bindings: &Bindings<Rational>,
+    digits: usize,
+) -> impl Fn(&str) -> Result<Rational, EvalError> + '_ {
+    move |name| match bindings.get(name) {
+        Some(value) => Ok(value.clone()),
+        None if name == r"\pi" => Ok(pi(digits + GUARD_DIGITS)),
+        None if name == "e" => Ok(e(digits + GUARD_DIGITS)),
+        None => Err(EvalError::UnboundVariable(name.to_owned())),
+    }
+}
+
+fn pow10(digits: usize) -> BigInt {
+    num_traits::pow(BigInt::from(10), digits)
+}
+
+/// `arctan(1 / x)`, scaled by `scale`, using its Taylor series.
+fn arctan_inv(x: u32, scale: &BigInt) -> BigInt {
+    let x_squared = BigInt::from(x * x);
+    let mut power = scale / x;
+    let mut sum = BigInt::zero();
+    let mut n = 1u32;
+    while !power.is_zero() {
+        let term = &power / n;
+        if n % 4 == 1 {
+            sum += term;
+        } else {
+            sum -= term;
+        }
+        power /= &x_squared;
+        n += 2;
+    }
+    sum
+}
+
+/// `π` to `digits` decimal places, using Machin's formula.
+fn pi(digits: usize) -> Rational {
+    let scale = pow10(digits);
+    let pi = (arctan_inv(5, &scale) * 16) - (arctan_inv(239, &scale) * 4);
+    Rational::new(pi, scale)
+}
+
+/// `e` to `digits` decimal places, as the sum of `1 / k!`.
+fn e(digits: usize) -> Rational {
+    let scale = pow10(digits);
+    let mut term = scale.clone();
+    let mut sum = BigInt::zero();
+    let mut k = 1u32;
+    while !term.is_zero() {
+        sum += &term;
+        term /= k;
+        k += 1;
+    }
+    Rational::new(sum, scale)
+}
+
+/// A decimal number with a fixed number of digits after the decimal point.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Decimal {
+    /// The value multiplied by `10^digits`.
+    scaled: BigInt,
+    digits: usize,
+}
+
+impl Decimal {
+    /// Rounds to the nearest decimal with `digits` digits after the decimal point,
+    /// rounding halfway cases away from zero.
+    pub fn from_rational(value: &Rational, digits: usize) -> Self {
+        let scaled = (value * Rational::from_integer(pow10(digits)))
+            .round()
+            .to_integer();
+        Self { scaled, digits }
+    }
+    pub fn to_rational(&self) -> Rational {
+        Rational::new(self.scaled.clone(), pow10(self.digits))
+    }
+    #[inline]
+    pub fn digits(&self) -> usize {
+        self.digits
+    }
+}
+
+impl std::fmt::Display for Decimal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let sign = if self.scaled.is_negative() { "-" } else { "" };
+        let abs = self.scaled.abs().to_string();
+        if self.digits == 0 {
+            return write!(f, "{sign}{abs}");
+        }
+        let abs = format!("{abs:0>width$}", width = self.digits + 1);
+        let (whole, fraction) = abs.split_at(abs.len() - self.digits);
+        write!(f, "{sign}{whole}.{fraction}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{constant::Constant, expression::AsExpression, math};
+
+    use super::*;
+
+    #[test]
+    fn test_evaluate() {
+        let x = Constant::new("x");
+        let y = Constant::new("y");
+        let pi = Constant::new(r"\pi");
+        let bindings = Bindings::new().bind("x", 2.0).bind("y", 5.0);
+
+        let exp = math![x + y].expr();
+        assert_eq!(exp.evaluate(&bindings), Ok(7.0));
+
+        let exp = math![x - (y * x)].expr();
+        assert_eq!(exp.evaluate(&bindings), Ok(-8.0));
+
+        let exp = math![(-(x + y)) * (-x) * y].expr();
+        assert_eq!(exp.evaluate(&bindings), Ok(70.0));
+
+        let exp = math![pi * x].expr();
+        assert_eq!(exp.evaluate(&bindings), Ok(std::f64::consts::PI * 2.0));
+
+        let bindings = Bindings::new().bind("π", 3.0);
+        assert_eq!(math![pi * pi].expr().evaluate(&bindings), Ok(9.0));
+    }
+
+    #[test]
+    fn test_unbound_variables() {
+        let x = Constant::new("x");
+        let pi = Constant::new(r"\pi");
+
+        let exp = math![x + pi].expr();
+        let err = exp.evaluate(&Bindings::new()).unwrap_err();
+        assert_eq!(err, EvalError::UnboundVariable("x".to_owned()));
+
+        let err = exp.evaluate(&Bindings::empty().bind("x", 1.0)).unwrap_err();
+        insta::assert_display_snapshot!(err, @"No value was given for π");
+    }
+
+    #[test]
+    fn test_evaluate_exact() {
+        let x = Constant::new("x");
+        let y = Constant::new("y");
+        let pi = Constant::new(r"\pi");
+        let bindings = Bindings::empty()
+            .bind("x", rational(1, 3))
+            .bind("y", rational(-3, 4));
+
+        let exp = math![(x * y) + (-x)].expr();
+        assert_eq!(exp.evaluate_exact(&bindings), Ok(rational(-7, 12)));
+
+        let exp = math![x * (-(y * y * y))].expr();
+        assert_eq!(exp.evaluate_exact(&bindings), Ok(rational(9, 64)));
+
+        let exp = math![x + pi].expr();
+        let err = exp.evaluate_exact(&bindings).unwrap_err();
+        assert_eq!(err, EvalError::NotExact(r"\pi".to_owned()));
+        insta::assert_display_snapshot!(err, @"π cannot be represented exactly as a rational number");
+    }
+
+    #[test]
+    fn test_evaluate_decimal() {
+        let x = Constant::new("x");
+        let e = Constant::new("e");
+        let pi = Constant::new(r"\pi");
+        let bindings = Bindings::empty().bind("x", rational(1, 3));
+
+        let exp = math![pi].expr();
+        insta::assert_display_snapshot!(
+            exp.evaluate_decimal(&bindings, 50).unwrap(),
+            @"3.14159265358979323846264338327950288419716939937511"
+        );
+
+        let exp = math![e].expr();
+        insta::assert_display_snapshot!(
+            exp.evaluate_decimal(&bindings, 30).unwrap(),
+            @"2.718281828459045235360287471353"
+        );
+
+        let exp = math![(-x) * pi].expr();
+        insta::assert_display_snapshot!(
+            exp.evaluate_decimal(&bindings, 20).unwrap(),
+            @"-1.04719755119659774615"
+        );
+
+        let exp = math![x + x].expr();
+        insta::assert_display_snapshot!(exp.evaluate_decimal(&bindings, 3).unwrap(), @"0.667");
+        insta::assert_display_snapshot!(exp.evaluate_decimal(&bindings, 0).unwrap(), @"1");
+
+        let exp = math![(-x) * x].expr();
+        insta::assert_display_snapshot!(exp.evaluate_decimal(&bindings, 5).unwrap(), @"-0.11111");
+    }
+}
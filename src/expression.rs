@@ -4,36 +4,36 @@ use std::sync::atomic::{AtomicU64, Ordering};
 use crate::{
     annotated_expression::Annotation,
     constant::Constant,
+    evaluate::{
+        lookup_decimal, lookup_exact, lookup_f64, Bindings, Decimal, EvalError, Evaluable, Number,
+        Rational,
+    },
     negation::Negation,
     product::Product,
+    sexpr::write_sexpr,
     sum::Sum,
-    token_stream::{MathPrintToken, TokenStream},
+    token_stream::{print_for_target, MathPrintToken, TokenStream},
     traverse::Traversable,
-    PrintOpts, Printable,
+    PrintOpts, PrintTarget, Printable,
 };
 
 pub(crate) const PRECEDENCE_SUM: usize = 1;
 pub(crate) const PRECEDENCE_NEGATION: usize = 2;
 pub(crate) const PRECEDENCE_PRODUCT: usize = 3;
 pub(crate) const PRECEDENCE_CONSTANT: usize = 4;
-pub(crate) const DEFAULT_PRINT_OPTS: PrintOpts = PrintOpts {
-    target: crate::PrintTarget::MathPrint,
-};
+pub(crate) const DEFAULT_PRINT_OPTS: PrintOpts = PrintOpts::default_for(PrintTarget::MathPrint);
 
-pub(crate) type ExpressionId = u64;
+/// Identifies an expression node, for annotations to point at.
+pub type ExpressionId = u64;
 
 pub(crate) fn gen_id() -> u64 {
     static COUNTER: AtomicU64 = AtomicU64::new(1);
     COUNTER.fetch_add(1, Ordering::Relaxed)
 }
 
-pub(crate) fn counterplus() {
-    static COUNTERR: AtomicU64 = AtomicU64::new(0);
-    println!("Counter: {}", COUNTERR.fetch_add(1, Ordering::Relaxed));
-}
-
+/// An expression tree. Nodes are reference counted, so cloning is cheap.
 #[derive(Clone)]
-pub(crate) enum Expression {
+pub enum Expression {
     Constant(Rc<Constant>),
     Product(Rc<Product>),
     Sum(Rc<Sum>),
@@ -49,7 +49,7 @@ impl Expression {
             Expression::Constant(..) => PRECEDENCE_CONSTANT,
         }
     }
-    pub(crate) fn id(&self) -> ExpressionId {
+    pub fn id(&self) -> ExpressionId {
         match self {
             Expression::Sum(inner) => inner.id(),
             Expression::Product(inner) => inner.id(),
@@ -57,81 +57,126 @@ impl Expression {
             Expression::Constant(inner) => inner.id(),
         }
     }
+    /// Whether both expressions have the same tree shape and constant names, ignoring ids.
+    pub fn structurally_eq(&self, other: &Expression) -> bool {
+        fn terms_eq(a: &[Expression], b: &[Expression]) -> bool {
+            a.len() == b.len() && a.iter().zip(b).all(|(a, b)| a.structurally_eq(b))
+        }
+        match (self, other) {
+            (Expression::Constant(a), Expression::Constant(b)) => a.name() == b.name(),
+            (Expression::Product(a), Expression::Product(b)) => terms_eq(a.terms(), b.terms()),
+            (Expression::Sum(a), Expression::Sum(b)) => terms_eq(a.terms(), b.terms()),
+            (Expression::Negation(a), Expression::Negation(b)) => {
+                a.inner().structurally_eq(b.inner())
+            }
+            _ => false,
+        }
+    }
+    /// Evaluates the expression as a floating point number.
+    pub fn evaluate(&self, bindings: &Bindings) -> Result<f64, EvalError> {
+        self.evaluate_with(&lookup_f64(bindings))
+    }
+    /// Evaluates the expression exactly.
+    /// Fails with [`EvalError::NotExact`] if it depends on an unbound `π` or `e`.
+    pub fn evaluate_exact(&self, bindings: &Bindings<Rational>) -> Result<Rational, EvalError> {
+        self.evaluate_with(&lookup_exact(bindings))
+    }
+    /// Evaluates the expression to `digits` digits after the decimal point.
+    /// Unbound `π` and `e` are computed to as many digits as needed.
+    pub fn evaluate_decimal(
+        &self,
+        bindings: &Bindings<Rational>,
+        digits: usize,
+    ) -> Result<Decimal, EvalError> {
+        let value = self.evaluate_with(&lookup_decimal(bindings, digits))?;
+        Ok(Decimal::from_rational(&value, digits))
+    }
+    /// The part of the expression at `path`. Each index picks a term of a sum or product,
+    /// or `0` for the inside of a negation, so `[]` is the whole expression.
+    pub fn subexpression(&self, path: &[usize]) -> Option<&Expression> {
+        path.iter()
+            .try_fold(self, |expr, &index| expr.child_iter().nth(index))
+    }
+    /// The same expression as a separate node, so annotating it doesn't highlight
+    /// other places the node is used. Its children are still shared.
+    pub(crate) fn with_new_id(&self) -> Expression {
+        match self {
+            Expression::Constant(constant) => (&constant.with_new_id()).into(),
+            _ => Expression::from_children(self, self.child_iter().cloned().collect()),
+        }
+    }
+    /// Prints the expression for the target. [`Display`](std::fmt::Display) prints it as MathPrint.
+    pub fn render(&self, target: PrintTarget, print_opts: &PrintOpts) -> String {
+        print_for_target(&self.print(target, print_opts, &[]), target, print_opts)
+    }
 }
 
 impl std::fmt::Display for Expression {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.print(&DEFAULT_PRINT_OPTS, &[]))
+        write!(
+            f,
+            "{}",
+            self.print(PrintTarget::MathPrint, &DEFAULT_PRINT_OPTS, &[])
+        )
     }
 }
 
+/// Prints the exact tree shape as an s-expression, like `(+ x (- y))`.
 impl std::fmt::Debug for Expression {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Expression::Constant(constant) => {
-                write!(f, "Expression::Constant({})", constant.expr())
-            }
-            Expression::Product(product) => {
-                write!(
-                    f,
-                    "Expression::Product {{\n  {}\n}}",
-                    product
-                        .terms()
-                        .iter()
-                        .enumerate()
-                        .map(|(i, term)| {
-                            if i == 0 {
-                                format!("{}", term)
-                            } else {
-                                format!(",\n  {}", term)
-                            }
-                        })
-                        .collect::<String>()
-                )
-            }
-            Expression::Sum(sum) => {
-                write!(
-                    f,
-                    "Expression::Sum {{\n  {}\n}}",
-                    sum.terms()
-                        .iter()
-                        .enumerate()
-                        .map(|(i, term)| {
-                            if i == 0 {
-                                format!("{}", term)
-                            } else {
-                                format!(",\n  {}", term)
-                            }
-                        })
-                        .collect::<String>()
-                )
-            }
-            Expression::Negation(negation) => {
-                write!(f, "Expression::Negation {{\n  {}\n}}", negation.inner())
-            }
-        }
+        write_sexpr(self, f)
     }
 }
 
+/// Surrounds the tokens with every annotation targeting `id`.
+pub(crate) fn annotate(
+    id: ExpressionId,
+    annotations: &[Annotation],
+    tokens: TokenStream,
+) -> TokenStream {
+    let annotations: Vec<&Annotation> = annotations
+        .iter()
+        .filter(|annotation| annotation.target_id == id)
+        .collect();
+    annotations
+        .iter()
+        .map(|annotation| MathPrintToken::AnnotationStart {
+            kind: annotation.kind,
+            label: annotation.label.clone(),
+        })
+        .chain(tokens)
+        .chain(annotations.iter().map(|_| MathPrintToken::AnnotationEnd))
+        .collect()
+}
+
 impl Printable for Expression {
-    fn print<'a>(&'a self, print_opts: &'a PrintOpts, annotations: &[Annotation]) -> TokenStream {
+    fn print<'a>(
+        &'a self,
+        target: PrintTarget,
+        print_opts: &'a PrintOpts,
+        annotations: &[Annotation],
+    ) -> TokenStream {
         let inner = match self {
-            Expression::Constant(constant) => constant.print(print_opts, annotations),
-            Expression::Product(product) => product.print(print_opts, annotations),
-            Expression::Sum(sum) => sum.print(print_opts, annotations),
-            Expression::Negation(neg) => neg.print(print_opts, annotations),
+            Expression::Constant(constant) => constant.print(target, print_opts, annotations),
+            Expression::Product(product) => product.print(target, print_opts, annotations),
+            Expression::Sum(sum) => sum.print(target, print_opts, annotations),
+            Expression::Negation(neg) => neg.print(target, print_opts, annotations),
         };
-        let id = self.id();
-        if annotations
-            .iter()
-            .any(|annotation| annotation.target_id == id)
-        {
-            std::iter::once(MathPrintToken::AnnotationStart)
-                .chain(inner)
-                .chain(std::iter::once(MathPrintToken::AnnotationEnd))
-                .collect()
-        } else {
-            inner
+        annotate(self.id(), annotations, inner)
+    }
+}
+
+impl Evaluable for Expression {
+    fn evaluate_with<V, F>(&self, lookup: &F) -> Result<V, EvalError>
+    where
+        V: Number,
+        F: Fn(&str) -> Result<V, EvalError>,
+    {
+        match self {
+            Expression::Constant(constant) => constant.evaluate_with(lookup),
+            Expression::Product(product) => product.evaluate_with(lookup),
+            Expression::Sum(sum) => sum.evaluate_with(lookup),
+            Expression::Negation(neg) => neg.evaluate_with(lookup),
         }
     }
 }
@@ -147,7 +192,6 @@ impl Traversable for Expression {
     }
 
     fn from_children(original: &Self, children: Vec<Expression>) -> Expression {
-        counterplus();
         match original {
             Expression::Constant(original) => (&Constant::from_children(original, children)).into(),
             Expression::Product(original) => Product::from_children(original, children).into(),
@@ -157,7 +201,15 @@ impl Traversable for Expression {
     }
 }
 
-pub(crate) trait AsExpression {
+impl From<&Expression> for Expression {
+    #[inline]
+    fn from(expr: &Expression) -> Self {
+        expr.clone()
+    }
+}
+
+/// Converts expression nodes into [`Expression`]s, like `Sum::new(terms).expr()`.
+pub trait AsExpression {
     fn expr(self) -> Expression;
 }
 
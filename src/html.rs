@@ -0,0 +1,284 @@
+use std::{fs, io, path::Path};
+
+use crate::{
+    step::{uses_katex, Step, StepPrintOpts},
+    token_stream::escape_html,
+    PrintOpts, PrintTarget,
+};
+
+/// Styles for highlighted expressions and nested substeps.
+const STEP_CSS: &str = r###"
+body {
+    font-size: 20px;
+}
+.katex .enclosing.hl, math .hl, .expression .hl,
+.katex .enclosing.hl-added, math .hl-added, .expression .hl-added,
+.katex .enclosing.hl-removed, math .hl-removed, .expression .hl-removed,
+.katex .enclosing.hl-cancelled-a, math .hl-cancelled-a, .expression .hl-cancelled-a,
+.katex .enclosing.hl-cancelled-b, math .hl-cancelled-b, .expression .hl-cancelled-b {
+  background: #ffcb0094;
+  display: inline-block;
+  --padding-x: 0.05em;
+  --padding-y: 0em;
+  padding: var(--padding-y) var(--padding-x);
+  margin: calc(-1 * var(--padding-y)) calc(-1 * var(--padding-x));
+  border-radius: 0.3em;
+}
+.katex .enclosing.hl-added, math .hl-added, .expression .hl-added {
+  background: #2da44e59;
+}
+.katex .enclosing.hl-removed, math .hl-removed, .expression .hl-removed {
+  background: #ff818266;
+  text-decoration: line-through;
+}
+.katex .enclosing.hl-cancelled-a, math .hl-cancelled-a, .expression .hl-cancelled-a {
+  background: #54aeff66;
+}
+.katex .enclosing.hl-cancelled-b, math .hl-cancelled-b, .expression .hl-cancelled-b {
+  background: #c297ff66;
+}
+.expression {
+  white-space: pre;
+}
+.labeled {
+  display: inline-flex;
+  flex-direction: column;
+  align-items: center;
+}
+.labeled .label {
+  font-size: 0.6em;
+}
+.substeps {
+    margin-left: 2em;
+    position: relative;
+}
+.substeps::before {
+    display: block;
+    content: '';
+    width: 0.3em;
+    height: 100%;
+    position: absolute;
+    background: #0000002e;
+    left: -2em;
+    border-radius: 1000px;
+}
+
+"###;
+
+/// KaTeX's stylesheet, from the release that `vendor/katex/download.sh` fetches.
+#[cfg(feature = "embed-katex")]
+const KATEX_CSS: &str = include_str!("../vendor/katex/katex.min.css");
+
+#[cfg(feature = "embed-katex")]
+macro_rules! katex_fonts {
+    ($($name:literal),* $(,)?) => {
+        &[$((
+            concat!("fonts/KaTeX_", $name, ".woff2"),
+            include_bytes!(concat!("../vendor/katex/fonts/KaTeX_", $name, ".woff2")) as &[u8],
+        )),*]
+    };
+}
+
+/// The fonts KaTeX's stylesheet links to, by URL.
+/// Every browser that can display KaTeX supports WOFF2, so the other font formats are left out.
+#[cfg(feature = "embed-katex")]
+const KATEX_FONTS: &[(&str, &[u8])] = katex_fonts![
+    "AMS-Regular",
+    "Caligraphic-Bold",
+    "Caligraphic-Regular",
+    "Fraktur-Bold",
+    "Fraktur-Regular",
+    "Main-Bold",
+    "Main-BoldItalic",
+    "Main-Italic",
+    "Main-Regular",
+    "Math-BoldItalic",
+    "Math-Italic",
+    "SansSerif-Bold",
+    "SansSerif-Italic",
+    "SansSerif-Regular",
+    "Script-Regular",
+    "Size1-Regular",
+    "Size2-Regular",
+    "Size3-Regular",
+    "Size4-Regular",
+    "Typewriter-Regular",
+];
+
+/// Options for [`export_html`].
+pub struct HtmlOptions {
+    pub title: String,
+    /// LaTeX and Typst are rendered through KaTeX when the `embed-katex` feature is enabled,
+    /// and as MathML otherwise.
+    pub target: PrintTarget,
+    pub print_opts: StepPrintOpts,
+}
+
+impl HtmlOptions {
+    pub fn new(target: PrintTarget) -> Self {
+        Self {
+            title: "Steps".to_owned(),
+            target,
+            print_opts: StepPrintOpts::uniform(PrintOpts::default_for(target)),
+        }
+    }
+}
+
+/// Prints the steps as a standalone HTML document.
+/// Everything it needs is embedded, so it can be opened without a network connection.
+pub fn html_document(step: &Step, options: &HtmlOptions) -> String {
+    // Without its stylesheet and fonts, KaTeX's output can't be displayed
+    let target = if uses_katex(options.target) && !cfg!(feature = "embed-katex") {
+        PrintTarget::MathML
+    } else {
+        options.target
+    };
+    let katex_css = if uses_katex(target) {
+        format!("<style>{}</style>\n", katex_css())
+    } else {
+        String::new()
+    };
+    format!(
+        r###"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>{}</title>
+{katex_css}<style>{STEP_CSS}</style>
+</head>
+<body>
+{}
+</body>
+</html>
+"###,
+        escape_html(&options.title),
+        step.html_print(target, &options.print_opts).trim()
+    )
+}
+
+/// Writes the steps to `path` as a standalone HTML document, see [`html_document`].
+pub fn export_html(step: &Step, options: &HtmlOptions, path: &Path) -> io::Result<()> {
+    fs::write(path, html_document(step, options))
+}
+
+/// KaTeX's stylesheet, with the fonts it links to embedded.
+#[cfg(feature = "embed-katex")]
+fn katex_css() -> String {
+    embed_katex_css(KATEX_CSS, KATEX_FONTS)
+}
+
+#[cfg(not(feature = "embed-katex"))]
+fn katex_css() -> String {
+    unreachable!("KaTeX is only used in documents with the `embed-katex` feature")
+}
+
+/// Replaces the fonts KaTeX's stylesheet links to with data URLs.
+/// Sources for fonts that aren't in `fonts` are left out.
+#[cfg(feature = "embed-katex")]
+fn embed_katex_css(css: &str, fonts: &[(&str, &[u8])]) -> String {
+    let mut embedded = String::with_capacity(css.len());
+    let mut rest = css;
+    while let Some(start) = rest.find("src:") {
+        let end = start + rest[start..].find([';', '}']).unwrap_or(rest.len() - start);
+        embedded.push_str(&rest[..start]);
+        let sources: Vec<String> = rest[start + "src:".len()..end]
+            .split(',')
+            .filter_map(|source| embed_font(source, fonts))
+            .collect();
+        embedded.push_str(&format!("src:{}", sources.join(",")));
+        rest = &rest[end..];
+    }
+    embedded.push_str(rest);
+    embedded
+}
+
+/// Replaces the URL in a `url(...) format(...)` font source with the font it points to.
+#[cfg(feature = "embed-katex")]
+fn embed_font(source: &str, fonts: &[(&str, &[u8])]) -> Option<String> {
+    let (before, after) = source.split_once("url(").unwrap_or(("", source));
+    let (url, after) = after.split_once(')').unwrap_or((after, ""));
+    let url = url.trim_matches(['"', '\'']);
+    let (_, font) = fonts.iter().find(|(name, _)| *name == url)?;
+    Some(format!(
+        "{before}url(data:font/woff2;base64,{}){after}",
+        base64(font)
+    ))
+}
+
+#[cfg(feature = "embed-katex")]
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, byte)| n | (*byte as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        constant::Constant, expression::AsExpression, math, simplify::simplify_excess_parens,
+    };
+
+    use super::*;
+
+    #[cfg(feature = "embed-katex")]
+    #[test]
+    fn test_base64() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+        assert_eq!(base64(b"foobar"), "Zm9vYmFy");
+    }
+
+    #[cfg(feature = "embed-katex")]
+    #[test]
+    fn test_embed_katex_css() {
+        let css = r#"@font-face{font-family:KaTeX_Main;src:url(fonts/KaTeX_Main-Regular.woff2) format("woff2"),url(fonts/KaTeX_Main-Regular.woff) format("woff")}.katex{font:normal 1.21em KaTeX_Main}"#;
+        let fonts: &[(&str, &[u8])] = &[("fonts/KaTeX_Main-Regular.woff2", b"font")];
+        insta::assert_snapshot!(embed_katex_css(css, fonts), @r###"@font-face{font-family:KaTeX_Main;src:url(data:font/woff2;base64,Zm9udA==) format("woff2")}.katex{font:normal 1.21em KaTeX_Main}"###);
+
+        let embedded = katex_css();
+        assert!(!embedded.contains("url(fonts/"));
+        assert_eq!(
+            embedded.matches("data:font/woff2").count(),
+            KATEX_FONTS.len()
+        );
+    }
+
+    #[test]
+    fn test_export_html() {
+        let x = Constant::new("x");
+        let y = Constant::new("y");
+        let z = Constant::new("z");
+        let steps = simplify_excess_parens(&math![(x * y) * z].expr());
+
+        let html = html_document(&steps, &HtmlOptions::new(PrintTarget::MathML));
+        assert!(html.contains("<math>"));
+        assert!(!html.contains("http"));
+        let html = html_document(&steps, &HtmlOptions::new(PrintTarget::LaTex));
+        assert_eq!(
+            html.contains("data:font/woff2"),
+            cfg!(feature = "embed-katex")
+        );
+
+        let out = std::env::temp_dir().join(format!("math-thing-test-html-{}", std::process::id()));
+        fs::create_dir_all(&out).unwrap();
+        let path = out.join("steps.html");
+        export_html(&steps, &HtmlOptions::new(PrintTarget::LaTex), &path).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), html);
+        fs::remove_dir_all(&out).unwrap();
+    }
+}
@@ -0,0 +1,622 @@
+//! The JSON format for expressions and steps, so front ends can render derivations themselves
+//! and saved derivations can be loaded again.
+//! It's described by `schema/step.schema.json`.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    annotated_expression::{AnnotatedExpression, Annotation, AnnotationKind},
+    constant::Constant,
+    expression::{AsExpression, Expression, ExpressionId, DEFAULT_PRINT_OPTS},
+    negation::Negation,
+    product::Product,
+    step::Step,
+    sum::Sum,
+    token_stream::{latex_print, math_print},
+    traverse::Traversable,
+    PrintOpts, PrintTarget, Printable,
+};
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub(crate) enum ExpressionJson {
+    Constant {
+        id: ExpressionId,
+        /// The name as LaTeX, like `x` or `\pi`.
+        name: String,
+    },
+    Sum {
+        id: ExpressionId,
+        terms: Vec<ExpressionJson>,
+    },
+    Product {
+        id: ExpressionId,
+        terms: Vec<ExpressionJson>,
+    },
+    Negation {
+        id: ExpressionId,
+        inner: Box<ExpressionJson>,
+    },
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct AnnotationJson {
+    pub(crate) target_id: ExpressionId,
+    pub(crate) kind: AnnotationKind,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) label: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) change: Option<usize>,
+}
+
+/// A whole expression along with its annotations,
+/// and how it's printed for clients that don't print expressions themselves.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct RenderedExpressionJson {
+    pub(crate) expression: ExpressionJson,
+    #[serde(default)]
+    pub(crate) annotations: Vec<AnnotationJson>,
+    /// Always written, but not needed when loading.
+    #[serde(default)]
+    pub(crate) latex: String,
+    #[serde(default)]
+    pub(crate) math_print: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct StepJson {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) label: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) annotated_expression: Option<RenderedExpressionJson>,
+    #[serde(default)]
+    pub(crate) substeps: Vec<StepJson>,
+    pub(crate) result: RenderedExpressionJson,
+}
+
+impl ExpressionJson {
+    fn id(&self) -> ExpressionId {
+        match self {
+            ExpressionJson::Constant { id, .. }
+            | ExpressionJson::Sum { id, .. }
+            | ExpressionJson::Product { id, .. }
+            | ExpressionJson::Negation { id, .. } => *id,
+        }
+    }
+}
+
+impl From<&Expression> for ExpressionJson {
+    fn from(expression: &Expression) -> Self {
+        let id = expression.id();
+        match expression {
+            Expression::Constant(constant) => ExpressionJson::Constant {
+                id,
+                name: constant.name().to_owned(),
+            },
+            Expression::Sum(sum) => ExpressionJson::Sum {
+                id,
+                terms: sum.terms().iter().map(ExpressionJson::from).collect(),
+            },
+            Expression::Product(product) => ExpressionJson::Product {
+                id,
+                terms: product.terms().iter().map(ExpressionJson::from).collect(),
+            },
+            Expression::Negation(neg) => ExpressionJson::Negation {
+                id,
+                inner: Box::new(neg.inner().into()),
+            },
+        }
+    }
+}
+
+impl From<&Annotation> for AnnotationJson {
+    fn from(annotation: &Annotation) -> Self {
+        Self {
+            target_id: annotation.target_id,
+            kind: annotation.kind,
+            label: annotation.label.clone(),
+            change: annotation.change,
+        }
+    }
+}
+
+impl RenderedExpressionJson {
+    pub(crate) fn new(expression: &Expression, annotations: &[Annotation]) -> Self {
+        let latex_opts = PrintOpts::default_for(PrintTarget::LaTex);
+        Self {
+            expression: expression.into(),
+            annotations: annotations.iter().map(AnnotationJson::from).collect(),
+            latex: latex_print(&expression.print(PrintTarget::LaTex, &latex_opts, annotations)),
+            math_print: math_print(&expression.print(
+                PrintTarget::MathPrint,
+                &DEFAULT_PRINT_OPTS,
+                annotations,
+            )),
+        }
+    }
+}
+
+impl From<&Step> for StepJson {
+    fn from(step: &Step) -> Self {
+        Self {
+            label: step.label.clone(),
+            annotated_expression: step.annotated_expression.as_ref().map(|annotated| {
+                RenderedExpressionJson::new(&annotated.expression, &annotated.annotations)
+            }),
+            substeps: step.substeps.iter().map(StepJson::from).collect(),
+            result: RenderedExpressionJson::new(&step.result, &step.result_annotations),
+        }
+    }
+}
+
+/// Writes the expression as JSON matching `#/$defs/renderedExpression` in `schema/step.schema.json`.
+pub fn expression_to_json(expression: &Expression) -> String {
+    serde_json::to_string_pretty(&RenderedExpressionJson::new(expression, &[])).unwrap()
+}
+
+/// Writes the step tree as JSON matching `schema/step.schema.json`.
+pub fn step_to_json(step: &Step) -> String {
+    serde_json::to_string_pretty(&StepJson::from(step)).unwrap()
+}
+
+/// Why JSON couldn't be loaded by [`expression_from_json`] or [`step_from_json`].
+#[derive(Debug)]
+pub enum LoadError {
+    Json(serde_json::Error),
+    /// An annotation targets an id that isn't in the expression it annotates.
+    UnknownAnnotationTarget(ExpressionId),
+    /// A constant's name is a LaTeX command that isn't supported.
+    UnknownLatex(String),
+    /// Two different nodes have the same id.
+    ConflictingId(ExpressionId),
+}
+
+impl std::fmt::Display for LoadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LoadError::Json(error) => write!(f, "Invalid JSON: {error}"),
+            LoadError::UnknownAnnotationTarget(id) => {
+                write!(
+                    f,
+                    "An annotation targets {id}, which isn't in its expression"
+                )
+            }
+            LoadError::UnknownLatex(name) => write!(f, "Unrecognized LaTeX code {name}"),
+            LoadError::ConflictingId(id) => write!(f, "Different nodes have the same id {id}"),
+        }
+    }
+}
+
+impl std::error::Error for LoadError {}
+
+impl From<serde_json::Error> for LoadError {
+    fn from(error: serde_json::Error) -> Self {
+        LoadError::Json(error)
+    }
+}
+
+/// Turns JSON back into expressions.
+/// New nodes get new ids, so ids in the JSON are mapped to the nodes created for them.
+/// Every occurrence of an id gets the same node, so nodes shared between steps stay shared,
+/// and annotations keep pointing at the right nodes.
+#[derive(Default)]
+struct Loader {
+    nodes: HashMap<ExpressionId, Expression>,
+}
+
+impl Loader {
+    fn expression(&mut self, json: &ExpressionJson) -> Result<Expression, LoadError> {
+        let expression = match json {
+            ExpressionJson::Constant { name, .. } => Constant::try_new(name)
+                .ok_or_else(|| LoadError::UnknownLatex(name.clone()))?
+                .expr(),
+            ExpressionJson::Sum { terms, .. } => Sum::new(self.terms(terms)?).expr(),
+            ExpressionJson::Product { terms, .. } => Product::new(self.terms(terms)?).expr(),
+            ExpressionJson::Negation { inner, .. } => Negation::new(self.expression(inner)?).expr(),
+        };
+        match self.nodes.get(&json.id()) {
+            None => {
+                self.nodes.insert(json.id(), expression.clone());
+                Ok(expression)
+            }
+            Some(existing) if same_node(existing, &expression) => Ok(existing.clone()),
+            Some(_) => Err(LoadError::ConflictingId(json.id())),
+        }
+    }
+    fn terms(&mut self, json: &[ExpressionJson]) -> Result<Vec<Expression>, LoadError> {
+        json.iter().map(|term| self.expression(term)).collect()
+    }
+    /// Loads an expression and the annotations on it.
+    fn annotated_expression(
+        &mut self,
+        json: &RenderedExpressionJson,
+    ) -> Result<AnnotatedExpression, LoadError> {
+        let expression = self.expression(&json.expression)?;
+        let mut ids = vec![];
+        collect_ids(&json.expression, &mut ids);
+        let annotations = json
+            .annotations
+            .iter()
+            .map(|annotation| {
+                if !ids.contains(&annotation.target_id) {
+                    return Err(LoadError::UnknownAnnotationTarget(annotation.target_id));
+                }
+                Ok(Annotation {
+                    target_id: self.nodes[&annotation.target_id].id(),
+                    kind: annotation.kind,
+                    label: annotation.label.clone(),
+                    change: annotation.change,
+                })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(AnnotatedExpression {
+            expression,
+            annotations,
+        })
+    }
+    fn step(&mut self, json: &StepJson) -> Result<Step, LoadError> {
+        let annotated_expression = json
+            .annotated_expression
+            .as_ref()
+            .map(|annotated_expression| self.annotated_expression(annotated_expression))
+            .transpose()?;
+        let substeps = json
+            .substeps
+            .iter()
+            .map(|substep| self.step(substep))
+            .collect::<Result<_, _>>()?;
+        let result = self.annotated_expression(&json.result)?;
+        Ok(Step {
+            label: json.label.clone(),
+            annotated_expression,
+            substeps,
+            result: result.expression,
+            result_annotations: result.annotations,
+        })
+    }
+}
+
+/// Whether two nodes loaded for the same id are the same node,
+/// with the same name or the same children.
+fn same_node(a: &Expression, b: &Expression) -> bool {
+    match (a, b) {
+        (Expression::Constant(a), Expression::Constant(b)) => a.name() == b.name(),
+        (Expression::Sum(_), Expression::Sum(_))
+        | (Expression::Product(_), Expression::Product(_))
+        | (Expression::Negation(_), Expression::Negation(_)) => a
+            .child_iter()
+            .map(Expression::id)
+            .eq(b.child_iter().map(Expression::id)),
+        _ => false,
+    }
+}
+
+fn collect_ids(json: &ExpressionJson, ids: &mut Vec<ExpressionId>) {
+    ids.push(json.id());
+    match json {
+        ExpressionJson::Constant { .. } => {}
+        ExpressionJson::Sum { terms, .. } | ExpressionJson::Product { terms, .. } => {
+            terms.iter().for_each(|term| collect_ids(term, ids));
+        }
+        ExpressionJson::Negation { inner, .. } => collect_ids(inner, ids),
+    }
+}
+
+/// Loads an expression written by [`expression_to_json`], along with any annotations on it.
+pub fn expression_from_json(json: &str) -> Result<AnnotatedExpression, LoadError> {
+    let json: RenderedExpressionJson = serde_json::from_str(json)?;
+    Loader::default().annotated_expression(&json)
+}
+
+/// Loads a step tree written by [`step_to_json`].
+pub fn step_from_json(json: &str) -> Result<Step, LoadError> {
+    let json: StepJson = serde_json::from_str(json)?;
+    Loader::default().step(&json)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{math, simplify::simplify_excess_parens};
+
+    use super::*;
+
+    /// Ids come from a global counter, so they're renumbered in the order they appear
+    /// to keep snapshots the same from run to run.
+    #[derive(Default)]
+    struct Renumber(HashMap<ExpressionId, ExpressionId>);
+
+    impl Renumber {
+        fn id(&mut self, id: &mut ExpressionId) {
+            let next = self.0.len() as ExpressionId;
+            *id = *self.0.entry(*id).or_insert(next);
+        }
+        fn expression(&mut self, expression: &mut ExpressionJson) {
+            match expression {
+                ExpressionJson::Constant { id, .. } => self.id(id),
+                ExpressionJson::Sum { id, terms } | ExpressionJson::Product { id, terms } => {
+                    self.id(id);
+                    terms.iter_mut().for_each(|term| self.expression(term));
+                }
+                ExpressionJson::Negation { id, inner } => {
+                    self.id(id);
+                    self.expression(inner);
+                }
+            }
+        }
+        fn rendered(&mut self, rendered: &mut RenderedExpressionJson) {
+            self.expression(&mut rendered.expression);
+            for annotation in &mut rendered.annotations {
+                self.id(&mut annotation.target_id);
+            }
+        }
+        fn step(&mut self, step: &mut StepJson) {
+            if let Some(annotated_expression) = &mut step.annotated_expression {
+                self.rendered(annotated_expression);
+            }
+            step.substeps
+                .iter_mut()
+                .for_each(|substep| self.step(substep));
+            self.rendered(&mut step.result);
+        }
+    }
+
+    #[test]
+    fn test_expression_json() {
+        let x = Constant::new("x");
+        let pi = Constant::new(r"\pi");
+
+        let exp = math![(-pi) * (x + pi)].expr();
+        let mut json: RenderedExpressionJson =
+            serde_json::from_str(&expression_to_json(&exp)).unwrap();
+        Renumber::default().rendered(&mut json);
+        insta::assert_snapshot!(serde_json::to_string_pretty(&json).unwrap(), @r###"
+        {
+          "expression": {
+            "kind": "product",
+            "id": 0,
+            "terms": [
+              {
+                "kind": "negation",
+                "id": 1,
+                "inner": {
+                  "kind": "constant",
+                  "id": 2,
+                  "name": "\\pi"
+                }
+              },
+              {
+                "kind": "sum",
+                "id": 3,
+                "terms": [
+                  {
+                    "kind": "constant",
+                    "id": 4,
+                    "name": "x"
+                  },
+                  {
+                    "kind": "constant",
+                    "id": 2,
+                    "name": "\\pi"
+                  }
+                ]
+              }
+            ]
+          },
+          "annotations": [],
+          "latex": "\\left(-\\pi\\right) \\left(x+\\pi\\right)",
+          "mathPrint": "(-π) * (x + π)"
+        }
+        "###);
+    }
+
+    #[test]
+    fn test_step_json_round_trip() {
+        let x = Constant::new("x");
+        let y = Constant::new("y");
+        let z = Constant::new("z");
+
+        let steps = simplify_excess_parens(&math![(x * y) * z].expr());
+        let json = step_to_json(&steps);
+        let mut parsed: StepJson = serde_json::from_str(&json).unwrap();
+        assert_eq!(serde_json::to_string_pretty(&parsed).unwrap(), json);
+        Renumber::default().step(&mut parsed);
+        insta::assert_snapshot!(serde_json::to_string_pretty(&parsed).unwrap(), @r###"
+        {
+          "label": "Simplify excess parentheses",
+          "substeps": [
+            {
+              "label": "Remove excess parentheses around product",
+              "annotatedExpression": {
+                "expression": {
+                  "kind": "product",
+                  "id": 0,
+                  "terms": [
+                    {
+                      "kind": "product",
+                      "id": 1,
+                      "terms": [
+                        {
+                          "kind": "constant",
+                          "id": 2,
+                          "name": "x"
+                        },
+                        {
+                          "kind": "constant",
+                          "id": 3,
+                          "name": "y"
+                        }
+                      ]
+                    },
+                    {
+                      "kind": "constant",
+                      "id": 4,
+                      "name": "z"
+                    }
+                  ]
+                },
+                "annotations": [
+                  {
+                    "targetId": 1,
+                    "kind": "focus",
+                    "change": 0
+                  }
+                ],
+                "latex": "\\left(\\htmlClass{hl}{x y}\\right) z",
+                "mathPrint": "(x * y) * z\n ^^^^^"
+              },
+              "substeps": [],
+              "result": {
+                "expression": {
+                  "kind": "product",
+                  "id": 5,
+                  "terms": [
+                    {
+                      "kind": "constant",
+                      "id": 6,
+                      "name": "x"
+                    },
+                    {
+                      "kind": "constant",
+                      "id": 7,
+                      "name": "y"
+                    },
+                    {
+                      "kind": "constant",
+                      "id": 4,
+                      "name": "z"
+                    }
+                  ]
+                },
+                "annotations": [
+                  {
+                    "targetId": 6,
+                    "kind": "added",
+                    "change": 0
+                  },
+                  {
+                    "targetId": 7,
+                    "kind": "added",
+                    "change": 0
+                  }
+                ],
+                "latex": "\\htmlClass{hl-added}{x} \\htmlClass{hl-added}{y} z",
+                "mathPrint": "x * y * z\n~   ~"
+              }
+            }
+          ],
+          "result": {
+            "expression": {
+              "kind": "product",
+              "id": 5,
+              "terms": [
+                {
+                  "kind": "constant",
+                  "id": 6,
+                  "name": "x"
+                },
+                {
+                  "kind": "constant",
+                  "id": 7,
+                  "name": "y"
+                },
+                {
+                  "kind": "constant",
+                  "id": 4,
+                  "name": "z"
+                }
+              ]
+            },
+            "annotations": [],
+            "latex": "x y z",
+            "mathPrint": "x * y * z"
+          }
+        }
+        "###);
+    }
+
+    /// The JSON with ids renumbered, so JSON for equal step trees is the same.
+    fn normalized_step_json(step: &Step) -> String {
+        let mut json = StepJson::from(step);
+        Renumber::default().step(&mut json);
+        serde_json::to_string_pretty(&json).unwrap()
+    }
+
+    #[test]
+    fn test_load_step() {
+        let x = Constant::new("x");
+        let y = Constant::new("y");
+        let z = Constant::new("z");
+
+        let steps = simplify_excess_parens(&math![(x * y) * (z * (x * y))].expr());
+        let loaded = step_from_json(&step_to_json(&steps)).unwrap();
+        assert_eq!(normalized_step_json(&loaded), normalized_step_json(&steps));
+        assert_eq!(loaded.to_string(), steps.to_string());
+
+        // Both steps share the product they start from with the step before them
+        let first_result = &loaded.substeps[0].result;
+        let second_input = &loaded.substeps[1]
+            .annotated_expression
+            .as_ref()
+            .unwrap()
+            .expression;
+        assert_eq!(first_result.id(), second_input.id());
+    }
+
+    #[test]
+    fn test_load_expression() {
+        let json = r#"{
+            "expression": {
+                "kind": "sum",
+                "id": 10,
+                "terms": [
+                    { "kind": "constant", "id": 11, "name": "x" },
+                    { "kind": "negation", "id": 12, "inner": { "kind": "constant", "id": 13, "name": "\\pi" } }
+                ]
+            },
+            "annotations": [{ "targetId": 12, "kind": "removed", "label": "gone" }]
+        }"#;
+        let loaded = expression_from_json(json).unwrap();
+        insta::assert_display_snapshot!(loaded, @r###"
+        x - π
+          ---
+          gone
+        "###);
+
+        let json = r#"{
+            "expression": { "kind": "constant", "id": 1, "name": "x" },
+            "annotations": [{ "targetId": 2, "kind": "focus" }]
+        }"#;
+        assert!(matches!(
+            expression_from_json(json),
+            Err(LoadError::UnknownAnnotationTarget(2))
+        ));
+
+        let json = r#"{ "expression": { "kind": "constant", "id": 1, "name": "\\alpha" } }"#;
+        assert!(matches!(
+            expression_from_json(json),
+            Err(LoadError::UnknownLatex(name)) if name == r"\alpha"
+        ));
+
+        let json = r#"{
+            "expression": {
+                "kind": "product",
+                "id": 1,
+                "terms": [
+                    { "kind": "constant", "id": 2, "name": "x" },
+                    { "kind": "constant", "id": 2, "name": "x" },
+                    { "kind": "constant", "id": 3, "name": "y" },
+                    { "kind": "constant", "id": 3, "name": "z" }
+                ]
+            }
+        }"#;
+        assert!(matches!(
+            expression_from_json(json),
+            Err(LoadError::ConflictingId(3))
+        ));
+    }
+}
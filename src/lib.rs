@@ -0,0 +1,464 @@
+//! Builds algebraic expressions, simplifies them step by step,
+//! and prints the steps as text, LaTeX, MathML, Typst, HTML or JSON.
+//!
+//! ```
+//! use math_thing::{math, simplify, AsExpression, Constant};
+//!
+//! let x = Constant::new("x");
+//! let y = Constant::new("y");
+//! let z = Constant::new("z");
+//! let steps = simplify(&math![(x * y) * z].expr());
+//! assert_eq!(steps.result.to_string(), "x * y * z");
+//! println!("{steps}");
+//! ```
+//!
+//! Expressions can also be built with [`Constant`], [`Sum`], [`Product`] and [`Negation`],
+//! with operators like `&x + &y * -&z`, or read with [`parse`] and [`parse_sexpr`].
+
+use token_stream::{MathPrintToken, TokenStream};
+
+mod annotated_expression;
+mod constant;
+mod equivalence;
+mod evaluate;
+mod expression;
+mod html;
+mod json;
+mod negation;
+mod ops;
+mod parse;
+mod parser;
+mod product;
+mod sexpr;
+mod simplify;
+mod step;
+mod sum;
+mod token_stream;
+mod traverse;
+mod verify;
+#[cfg(feature = "wasm")]
+mod wasm;
+
+pub use annotated_expression::{AnnotatedExpression, Annotation, AnnotationKind};
+pub use constant::Constant;
+pub use equivalence::{are_equivalent, canonical_form, Equivalence};
+pub use evaluate::{rational, Bindings, Decimal, EvalError, Rational};
+pub use expression::{AsExpression, Expression, ExpressionId};
+pub use html::{export_html, html_document, HtmlOptions};
+pub use json::{expression_from_json, expression_to_json, step_from_json, step_to_json, LoadError};
+pub use negation::Negation;
+pub use parse::{parse, ParseError};
+pub use product::Product;
+pub use sexpr::{parse_sexpr, sexpr_print, SexprError};
+pub use simplify::{find_rule, simplify, simplify_excess_parens, simplify_with, Rule, RULES};
+pub use step::{Step, StepPrintOpts};
+pub use sum::Sum;
+pub use verify::{find_counterexample, verify, UnsoundStep};
+
+/// Builds an expression from infix notation, like `math![(x * y) + (-z)]`.
+/// Constants are written as the variables holding them, and other expressions go in braces,
+/// like `math![x * { inner.clone() }]`.
+/// Every operation needs its own parentheses, except chains of the same operator.
+///
+/// ```
+/// use math_thing::{AsExpression, Constant};
+///
+/// let x = Constant::new("x");
+/// let y = Constant::new("y");
+/// let z = Constant::new("z");
+/// let expression = math_thing::math![(x * y) + (-(y + z))].expr();
+/// assert_eq!(expression.to_string(), "x * y - (y + z)");
+/// ```
+#[macro_export]
+macro_rules! math {
+    ($a:ident) => {&$a};
+    ($a:literal) => {$a};
+    ({$a:expr}) => {$a};
+    ((-$a:ident)) => {
+        $crate::Negation::new($crate::math!($a).into())
+    };
+    ((-$a:literal)) => {
+        $crate::Negation::new($crate::math!($a).into())
+    };
+    ((-($($a:tt)*))) => {
+        $crate::Negation::new($crate::math!($($a)*).into())
+    };
+    // Remove extra parentheses
+    (($($a:tt)*)) => {
+        $crate::math!($($a)*)
+    };
+    // Expand a+b+c+d...
+    ($a:tt $(+ $b:tt)+) => {
+        $crate::Sum::new(vec![$crate::math!($a).into(), $($crate::math!($b).into()),*])
+    };
+    // Expand a-b
+    ($a:tt - $b:tt) => {
+        $crate::Sum::new(vec![
+            $crate::math!($a).into(),
+            $crate::Negation::new($crate::math!($b).into()).into(),
+        ])
+    };
+    // Expand a*b*c*d...
+    ($a:tt $(* $b:tt)+) => {
+        $crate::Product::new(vec![$crate::math!($a).into(), $($crate::math!($b).into()),*])
+    };
+    // // Expand a/b
+    // ($a:tt / $b:tt) => {
+    //     Divide(math!($a), math!($b))
+    // };
+    // // Expand a^b
+    // ($a:tt ^ $b:tt) => {
+    //     Exponent(math!($a), math!($b))
+    // };
+}
+
+/// The format expressions are printed in.
+#[derive(Clone, Copy)]
+pub enum PrintTarget {
+    LaTex,
+    MathPrint,
+    /// Presentation MathML, which browsers can render without KaTeX
+    MathML,
+    Typst,
+}
+
+#[derive(Clone, Copy)]
+pub enum MultiplicationSign {
+    /// `x y`
+    Implicit,
+    /// `x * y`
+    Asterisk,
+    /// `x × y`
+    Times,
+    /// `x · y`
+    Dot,
+}
+
+#[derive(Clone, Copy)]
+pub enum GreekLetters {
+    /// `π`
+    Unicode,
+    /// `pi`, or `\pi` in LaTeX
+    Ascii,
+}
+
+#[derive(Clone, Copy)]
+pub enum Parentheses {
+    /// Parenthesize every nested sum, product, and negation, so the tree structure is visible.
+    Faithful,
+    /// Only add parentheses where leaving them out would change the meaning.
+    Minimal,
+}
+
+/// Style options for printing, which apply to every [`PrintTarget`].
+#[derive(Clone, Copy)]
+pub struct PrintOpts {
+    pub multiplication: MultiplicationSign,
+    /// Whether to put spaces around `+`, `-`, and the multiplication sign.
+    pub operator_spacing: bool,
+    pub greek: GreekLetters,
+    pub parentheses: Parentheses,
+    /// Long expressions are wrapped before top-level `+` and `-` to fit in this many characters.
+    pub line_width: Option<usize>,
+}
+
+impl PrintOpts {
+    /// The options that read most naturally in each target.
+    pub const fn default_for(target: PrintTarget) -> Self {
+        match target {
+            PrintTarget::MathPrint => PrintOpts {
+                multiplication: MultiplicationSign::Asterisk,
+                operator_spacing: true,
+                greek: GreekLetters::Unicode,
+                parentheses: Parentheses::Faithful,
+                line_width: None,
+            },
+            PrintTarget::LaTex => PrintOpts {
+                multiplication: MultiplicationSign::Implicit,
+                operator_spacing: false,
+                greek: GreekLetters::Ascii,
+                parentheses: Parentheses::Faithful,
+                line_width: None,
+            },
+            PrintTarget::MathML => PrintOpts {
+                multiplication: MultiplicationSign::Implicit,
+                operator_spacing: false,
+                greek: GreekLetters::Unicode,
+                parentheses: Parentheses::Faithful,
+                line_width: None,
+            },
+            PrintTarget::Typst => PrintOpts {
+                multiplication: MultiplicationSign::Implicit,
+                operator_spacing: true,
+                greek: GreekLetters::Ascii,
+                parentheses: Parentheses::Faithful,
+                line_width: None,
+            },
+        }
+    }
+    /// Formats a binary operator for the target, adding spaces around it if enabled.
+    /// `symbol` is the operator as text, and `word` is its name in LaTeX and Typst when it isn't ASCII.
+    fn operator(&self, target: PrintTarget, symbol: &str, word: Option<&str>) -> String {
+        match target {
+            PrintTarget::MathML => format!("<mo>{symbol}</mo>"),
+            PrintTarget::LaTex => match word {
+                Some(word) if self.operator_spacing => format!(" \\{word} "),
+                Some(word) => format!("\\{word} "),
+                None if self.operator_spacing => format!(" {symbol} "),
+                None => symbol.to_owned(),
+            },
+            // Typst needs spaces to separate words from variables
+            PrintTarget::Typst if word.is_some() => format!(" {} ", word.unwrap()),
+            PrintTarget::MathPrint | PrintTarget::Typst => {
+                if self.operator_spacing {
+                    format!(" {symbol} ")
+                } else {
+                    symbol.to_owned()
+                }
+            }
+        }
+    }
+    /// The sign in front of a negated expression, which is never spaced.
+    pub(crate) fn negative(&self, target: PrintTarget) -> &'static str {
+        match target {
+            PrintTarget::MathML => "<mo>-</mo>",
+            _ => "-",
+        }
+    }
+    pub(crate) fn plus(&self, target: PrintTarget) -> String {
+        self.operator(target, "+", None)
+    }
+    pub(crate) fn minus(&self, target: PrintTarget) -> String {
+        self.operator(target, "-", None)
+    }
+    pub(crate) fn times(&self, target: PrintTarget) -> String {
+        match (self.multiplication, target) {
+            // Invisible times, so screen readers still announce the multiplication
+            (MultiplicationSign::Implicit, PrintTarget::MathML) => "<mo>&#x2062;</mo>".to_owned(),
+            (MultiplicationSign::Implicit, PrintTarget::LaTex | PrintTarget::Typst) => {
+                " ".to_owned()
+            }
+            (MultiplicationSign::Implicit, PrintTarget::MathPrint) => {
+                if self.operator_spacing { " " } else { "" }.to_owned()
+            }
+            (MultiplicationSign::Asterisk, _) => self.operator(target, "*", None),
+            (MultiplicationSign::Times, _) => self.operator(target, "×", Some("times")),
+            (MultiplicationSign::Dot, _) => self.operator(target, "·", Some("cdot")),
+        }
+    }
+}
+
+trait Printable {
+    #[cfg(test)]
+    fn latex_with_annotations(&self, annotations: &[Annotation]) -> String {
+        token_stream::latex_print(&self.print(
+            PrintTarget::LaTex,
+            &PrintOpts::default_for(PrintTarget::LaTex),
+            annotations,
+        ))
+    }
+    #[cfg(test)]
+    fn latex(&self) -> String {
+        self.latex_with_annotations(&[])
+    }
+    #[cfg(test)]
+    fn mathml_with_annotations(&self, annotations: &[Annotation]) -> String {
+        token_stream::mathml_print(&self.print(
+            PrintTarget::MathML,
+            &PrintOpts::default_for(PrintTarget::MathML),
+            annotations,
+        ))
+    }
+    #[cfg(test)]
+    fn mathml(&self) -> String {
+        self.mathml_with_annotations(&[])
+    }
+    #[cfg(test)]
+    fn typst_with_annotations(&self, annotations: &[Annotation]) -> String {
+        token_stream::typst_print(&self.print(
+            PrintTarget::Typst,
+            &PrintOpts::default_for(PrintTarget::Typst),
+            annotations,
+        ))
+    }
+    #[cfg(test)]
+    fn typst(&self) -> String {
+        self.typst_with_annotations(&[])
+    }
+    fn print<'a>(
+        &'a self,
+        target: PrintTarget,
+        print_opts: &'a PrintOpts,
+        annotations: &[Annotation],
+    ) -> TokenStream;
+    fn print_with_parens<'a>(
+        &'a self,
+        target: PrintTarget,
+        print_opts: &'a PrintOpts,
+        annotations: &[Annotation],
+    ) -> TokenStream {
+        let inner = self.print(target, print_opts, annotations);
+        let parenthesized = match target {
+            PrintTarget::LaTex => tokens!["\\left(", inner, "\\right)"],
+            PrintTarget::MathPrint | PrintTarget::Typst => tokens!["(", inner, ")"],
+            PrintTarget::MathML => tokens!["<mrow><mo>(</mo>", inner, "<mo>)</mo></mrow>"],
+        };
+        std::iter::once(MathPrintToken::GroupStart)
+            .chain(parenthesized)
+            .chain(std::iter::once(MathPrintToken::GroupEnd))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::expression::DEFAULT_PRINT_OPTS;
+
+    use super::*;
+
+    #[test]
+    fn printing() {
+        let x = Constant::new("x");
+        let y = Constant::new("y");
+        let pi = Constant::new(r"\pi");
+
+        let exp = math![x * y * pi].expr();
+
+        insta::assert_display_snapshot!(exp, @"x * y * π");
+        insta::assert_snapshot!(exp.latex(), @r###"x y \pi"###);
+
+        let exp = math![x * (y * pi)].expr();
+        insta::assert_display_snapshot!(exp, @"x * (y * π)");
+        insta::assert_snapshot!(exp.latex(), @r###"x \left(y \pi\right)"###);
+
+        let exp = math![x + y + pi].expr();
+
+        insta::assert_display_snapshot!(exp, @"x + y + π");
+        insta::assert_snapshot!(exp.latex(), @r###"x+y+\pi"###);
+
+        let exp = math![x + y + (-pi)].expr();
+
+        insta::assert_display_snapshot!(exp, @"x + y - π");
+        insta::assert_snapshot!(exp.latex(), @r###"x+y-\pi"###);
+
+        let exp = math![(-(x + y)) + pi].expr();
+
+        insta::assert_display_snapshot!(exp, @"-(x + y) + π");
+        insta::assert_snapshot!(exp.latex(), @r###"-\left(x+y\right)+\pi"###);
+
+        let exp = math![(x * y * (y - pi)) + (-(x - pi))].expr();
+        insta::assert_display_snapshot!(exp, @"x * y * (y - π) - (x - π)");
+        insta::assert_snapshot!(exp.latex(), @r###"x y \left(y-\pi\right)-\left(x-\pi\right)"###);
+
+        let exp = math![((-pi) * x) + (y * (-y) * y) + (-(x * (pi - x)))].expr();
+        // Yes, the parens around the negative signs should be there.
+        // It makes it more clear when substitutions have happened.
+        // And the parens will be removed
+        // when the negative sign is moved outwards during simplification steps.
+        insta::assert_display_snapshot!(exp, @"(-π) * x + y * (-y) * y - x * (π - x)");
+        insta::assert_snapshot!(exp.latex(), @r###"\left(-\pi\right) x+y \left(-y\right) y-x \left(\pi-x\right)"###);
+
+        let exp = math![(-(pi * x * y))].expr();
+        insta::assert_display_snapshot!(exp, @"-π * x * y");
+        insta::assert_snapshot!(exp.latex(), @r###"-\pi x y"###);
+    }
+
+    #[test]
+    fn printing_options() {
+        let x = Constant::new("x");
+        let y = Constant::new("y");
+        let pi = Constant::new(r"\pi");
+        let exp = math![((x * y) * pi) + (-(x + pi)) + (y * (-x))].expr();
+        let print = |target, print_opts: &PrintOpts| exp.render(target, print_opts);
+
+        let opts = PrintOpts {
+            multiplication: MultiplicationSign::Times,
+            ..DEFAULT_PRINT_OPTS
+        };
+        insta::assert_snapshot!(print(PrintTarget::MathPrint, &opts), @"(x × y) × π - (x + π) + y × (-x)");
+        insta::assert_snapshot!(print(PrintTarget::LaTex, &opts), @r###"\left(x \times y\right) \times π - \left(x + π\right) + y \times \left(-x\right)"###);
+        insta::assert_snapshot!(print(PrintTarget::Typst, &opts), @"(x times y) times π - (x + π) + y times (-x)");
+
+        let opts = PrintOpts {
+            multiplication: MultiplicationSign::Dot,
+            operator_spacing: false,
+            greek: GreekLetters::Ascii,
+            ..DEFAULT_PRINT_OPTS
+        };
+        insta::assert_snapshot!(print(PrintTarget::MathPrint, &opts), @"(x·y)·pi-(x+pi)+y·(-x)");
+        insta::assert_snapshot!(print(PrintTarget::LaTex, &opts), @r###"\left(x\cdot y\right)\cdot \pi-\left(x+\pi\right)+y\cdot \left(-x\right)"###);
+        insta::assert_snapshot!(print(PrintTarget::MathML, &opts), @"<math><mrow><mo>(</mo><mi>x</mi><mo>·</mo><mi>y</mi><mo>)</mo></mrow><mo>·</mo><mi>pi</mi><mo>-</mo><mrow><mo>(</mo><mi>x</mi><mo>+</mo><mi>pi</mi><mo>)</mo></mrow><mo>+</mo><mi>y</mi><mo>·</mo><mrow><mo>(</mo><mo>-</mo><mi>x</mi><mo>)</mo></mrow></math>");
+
+        let opts = PrintOpts {
+            parentheses: Parentheses::Minimal,
+            ..DEFAULT_PRINT_OPTS
+        };
+        insta::assert_snapshot!(print(PrintTarget::MathPrint, &opts), @"x * y * π - (x + π) + y * -x");
+        let opts = PrintOpts {
+            parentheses: Parentheses::Minimal,
+            ..PrintOpts::default_for(PrintTarget::LaTex)
+        };
+        insta::assert_snapshot!(print(PrintTarget::LaTex, &opts), @r###"x y \pi-\left(x+\pi\right)+y \left(-x\right)"###);
+    }
+
+    #[test]
+    fn printing_mathml() {
+        let x = Constant::new("x");
+        let y = Constant::new("y");
+        let pi = Constant::new(r"\pi");
+
+        let exp = math![x * y * pi].expr();
+        insta::assert_snapshot!(exp.mathml(), @"<math><mi>x</mi><mo>&#x2062;</mo><mi>y</mi><mo>&#x2062;</mo><mi>π</mi></math>");
+
+        let exp = math![(x * y * (y - pi)) + (-(x - pi))].expr();
+        insta::assert_snapshot!(exp.mathml(), @"<math><mi>x</mi><mo>&#x2062;</mo><mi>y</mi><mo>&#x2062;</mo><mrow><mo>(</mo><mi>y</mi><mo>-</mo><mi>π</mi><mo>)</mo></mrow><mo>-</mo><mrow><mo>(</mo><mi>x</mi><mo>-</mo><mi>π</mi><mo>)</mo></mrow></math>");
+
+        let inner = math![x + y].expr();
+        let annotations = [Annotation::new(&inner)];
+        let exp = math![(-pi) * { inner }].expr();
+        insta::assert_snapshot!(exp.mathml_with_annotations(&annotations), @r###"<math><mrow><mo>(</mo><mo>-</mo><mi>π</mi><mo>)</mo></mrow><mo>&#x2062;</mo><mrow><mo>(</mo><mrow class="hl"><mi>x</mi><mo>+</mo><mi>y</mi></mrow><mo>)</mo></mrow></math>"###);
+    }
+
+    #[test]
+    fn printing_typst() {
+        let x = Constant::new("x");
+        let y = Constant::new("y");
+        let pi = Constant::new(r"\pi");
+        let speed = Constant::new("speed");
+
+        let exp = math![x * y * pi].expr();
+        insta::assert_snapshot!(exp.typst(), @"x y pi");
+
+        let exp = math![(x * y * (y - pi)) + (-(x - speed))].expr();
+        insta::assert_snapshot!(exp.typst(), @r###"x y (y - pi) - (x - "speed")"###);
+
+        let inner = math![x + y].expr();
+        let annotations = [Annotation::new(&inner)];
+        let exp = math![(-pi) * { inner }].expr();
+        insta::assert_snapshot!(exp.typst_with_annotations(&annotations), @"(-pi) (#highlight[$x + y$])");
+    }
+
+    #[test]
+    fn simplify_parens_and_negatives() {
+        // let x = Constant::new("x");
+        // let y = Constant::new("y");
+        // let z = Constant::new("z");
+
+        // let exp = math![(-x) * (-y) * (-z)].expr();
+        // insta::assert_display_snapshot!(exp, @"(-x) * (-y) * (-z)");
+        // insta::assert_display_snapshot!(exp.simplify_parens_and_negatives(), @"-x * y * z");
+
+        // // TODO: these should all simplify fully and correctly
+        // let exp = math![((-x) * (-y)) * (-z)].expr();
+        // insta::assert_display_snapshot!(exp, @"((-x) * (-y)) * (-z)");
+        // insta::assert_display_snapshot!(exp.simplify_parens_and_negatives(), @"-(x * y) * z");
+
+        // let exp = math![((-x) * (-y)) * ((-z) * (-x))].expr();
+        // insta::assert_display_snapshot!(exp, @"((-x) * (-y)) * ((-z) * (-x))");
+        // insta::assert_display_snapshot!(exp.simplify_parens_and_negatives(), @"(x * y) * (z * x)");
+
+        // let exp = math![(-x) * ((-z) * (-x))].expr();
+        // insta::assert_display_snapshot!(exp, @"(-x) * ((-z) * (-x))");
+        // insta::assert_display_snapshot!(exp.simplify_parens_and_negatives(), @"-x * (z * x)");
+    }
+}
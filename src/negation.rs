@@ -1,14 +1,16 @@
 use crate::{
     annotated_expression::Annotation,
+    evaluate::{EvalError, Evaluable, Number},
     expression::{gen_id, Expression, ExpressionId, PRECEDENCE_NEGATION},
     token_stream::TokenStream,
     tokens,
     traverse::Traversable,
-    PrintOpts, Printable,
+    PrintOpts, PrintTarget, Printable,
 };
 
+/// The negative of an expression.
 #[derive(Clone)]
-pub(crate) struct Negation {
+pub struct Negation {
     inner: Expression,
     id: ExpressionId,
 }
@@ -26,19 +28,25 @@ impl Negation {
         &self.inner
     }
     #[inline]
-    pub(crate) fn id(&self) -> ExpressionId {
+    pub fn id(&self) -> ExpressionId {
         self.id
     }
 }
 
 impl Printable for Negation {
-    fn print<'a>(&'a self, print_opts: &'a PrintOpts, annotations: &[Annotation]) -> TokenStream {
+    fn print<'a>(
+        &'a self,
+        target: PrintTarget,
+        print_opts: &'a PrintOpts,
+        annotations: &[Annotation],
+    ) -> TokenStream {
         let inner = if self.inner.precedence() <= PRECEDENCE_NEGATION {
-            self.inner.print_with_parens(print_opts, annotations)
+            self.inner
+                .print_with_parens(target, print_opts, annotations)
         } else {
-            self.inner.print(print_opts, annotations)
+            self.inner.print(target, print_opts, annotations)
         };
-        tokens!("-", inner)
+        tokens!(std::iter::once(print_opts.negative(target).into()), inner)
     }
 }
 
@@ -49,6 +57,16 @@ impl From<Negation> for Expression {
     }
 }
 
+impl Evaluable for Negation {
+    fn evaluate_with<V, F>(&self, lookup: &F) -> Result<V, EvalError>
+    where
+        V: Number,
+        F: Fn(&str) -> Result<V, EvalError>,
+    {
+        Ok(-self.inner.evaluate_with(lookup)?)
+    }
+}
+
 impl Traversable for Negation {
     fn child_iter<'a>(&'a self) -> Box<dyn Iterator<Item = &'a Expression> + 'a> {
         Box::new(std::iter::once(&self.inner))
@@ -0,0 +1,99 @@
+//! Operators for building expressions, like `&x + &y * -&z`.
+//! Operands can be expressions or constants, by value or by reference.
+//!
+//! Each operator makes its own node, the way every pair of parentheses does in [`math!`](crate::math!),
+//! so `&x + &y + &z` is `(x + y) + z`. Use [`Sum::new`] and [`Product::new`] for longer chains.
+//! There's no `Div`, since expressions can't divide yet.
+
+use std::ops::{Add, Mul, Neg, Sub};
+
+use crate::{
+    constant::Constant,
+    expression::{AsExpression, Expression},
+    negation::Negation,
+    product::Product,
+    sum::Sum,
+};
+
+macro_rules! impl_binary_ops {
+    ($($lhs:ty, $rhs:ty;)*) => {$(
+        impl Add<$rhs> for $lhs {
+            type Output = Expression;
+            fn add(self, rhs: $rhs) -> Expression {
+                Sum::new(vec![self.into(), rhs.into()]).expr()
+            }
+        }
+        impl Sub<$rhs> for $lhs {
+            type Output = Expression;
+            fn sub(self, rhs: $rhs) -> Expression {
+                Sum::new(vec![self.into(), Negation::new(rhs.into()).expr()]).expr()
+            }
+        }
+        impl Mul<$rhs> for $lhs {
+            type Output = Expression;
+            fn mul(self, rhs: $rhs) -> Expression {
+                Product::new(vec![self.into(), rhs.into()]).expr()
+            }
+        }
+    )*};
+}
+
+impl_binary_ops! {
+    Expression, Expression;
+    Expression, &Expression;
+    Expression, &Constant;
+    &Expression, Expression;
+    &Expression, &Expression;
+    &Expression, &Constant;
+    &Constant, Expression;
+    &Constant, &Expression;
+    &Constant, &Constant;
+}
+
+macro_rules! impl_neg {
+    ($($operand:ty),*) => {$(
+        impl Neg for $operand {
+            type Output = Expression;
+            fn neg(self) -> Expression {
+                Negation::new(self.into()).expr()
+            }
+        }
+    )*};
+}
+
+impl_neg!(Expression, &Expression, &Constant);
+
+#[cfg(test)]
+mod tests {
+    use crate::{math, sexpr::sexpr_print};
+
+    use super::*;
+
+    #[test]
+    fn test_operators() {
+        let x = Constant::new("x");
+        let y = Constant::new("y");
+        let z = Constant::new("z");
+
+        let built = |expr: Expression| sexpr_print(&expr);
+        insta::assert_snapshot!(built(&x + &y * -&z), @"(+ x (* y (- z)))");
+        assert_eq!(built(&x + &y * -&z), built(math![x + (y * (-z))].expr()));
+        insta::assert_snapshot!(built((&x * &y) * &z), @"(* (* x y) z)");
+        assert_eq!(built((&x * &y) * &z), built(math![(x * y) * z].expr()));
+        insta::assert_snapshot!(built(&x - &y - -(&x + &z)), @"(+ (+ x (- y)) (- (- (+ x z))))");
+        insta::assert_snapshot!(built(-(-&x)), @"(- (- x))");
+
+        let Expression::Product(product) = &x * &x else {
+            unreachable!()
+        };
+        assert_eq!(product.terms()[0].id(), x.id());
+        assert_eq!(product.terms()[1].id(), x.id());
+
+        let sum = &x + &y;
+        insta::assert_snapshot!(built(&sum * -&sum), @"(* (+ x y) (- (+ x y)))");
+        let Expression::Product(product) = &sum * &z else {
+            unreachable!()
+        };
+        assert_eq!(product.terms()[0].id(), sum.id());
+    }
+}
@@ -0,0 +1,206 @@
+//! Reads expressions written the way MathPrint prints them, like `-(x + y) * z`.
+//!
+//! Parentheses are kept as nesting, so `(x * y) * z` is a product inside a product,
+//! and `x - y` is a sum with a negated term.
+//! Negation binds looser than multiplication, so `-x * y` is `-(x * y)`.
+//! Names are runs of letters, digits and underscores, or LaTeX commands like `\pi`,
+//! so `xy` is one constant and `x y` is a product.
+
+use crate::{
+    expression::{AsExpression, Expression},
+    negation::Negation,
+    parser::Parser,
+    product::Product,
+    sum::Sum,
+};
+
+/// Why [`parse`] couldn't read an expression.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    /// The input ended in the middle of an expression.
+    UnexpectedEnd,
+    /// An unexpected character, at the given byte offset.
+    Unexpected(usize, char),
+    /// A LaTeX command that isn't supported.
+    UnknownLatex(String),
+    /// Parentheses or negations nested too deeply, at the given byte offset.
+    TooDeep(usize),
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseError::UnexpectedEnd => write!(f, "Unexpected end of input"),
+            ParseError::Unexpected(offset, c) => write!(f, "Unexpected {c:?} at {offset}"),
+            ParseError::UnknownLatex(name) => write!(f, "Unrecognized LaTeX code {name}"),
+            ParseError::TooDeep(offset) => write!(f, "Nested too deeply at {offset}"),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+impl Parser<'_> {
+    /// Skips whitespace and returns the next character.
+    fn next_token(&mut self) -> Option<char> {
+        self.skip_whitespace();
+        self.peek()
+    }
+    fn unexpected(&self) -> ParseError {
+        match self.peek() {
+            Some(c) => ParseError::Unexpected(self.offset, c),
+            None => ParseError::UnexpectedEnd,
+        }
+    }
+    fn sum(&mut self) -> Result<Expression, ParseError> {
+        let mut terms = vec![self.negation()?];
+        loop {
+            match self.next_token() {
+                Some('+') => {
+                    self.bump();
+                    terms.push(self.negation()?);
+                }
+                Some('-') => {
+                    self.bump();
+                    terms.push(Negation::new(self.negation()?).expr());
+                }
+                _ => break,
+            }
+        }
+        Ok(if terms.len() == 1 {
+            terms.pop().unwrap()
+        } else {
+            Sum::new(terms).expr()
+        })
+    }
+    fn negation(&mut self) -> Result<Expression, ParseError> {
+        if self.next_token() == Some('-') {
+            self.bump();
+            Ok(Negation::new(self.nested(ParseError::TooDeep, Self::negation)?).expr())
+        } else {
+            self.product()
+        }
+    }
+    fn product(&mut self) -> Result<Expression, ParseError> {
+        let mut factors = vec![self.atom()?];
+        loop {
+            match self.next_token() {
+                Some('*' | '·' | '×') => {
+                    self.bump();
+                    factors.push(self.factor()?);
+                }
+                // Implicit multiplication
+                Some(c) if c == '(' || c == '\\' || is_name_char(c) => {
+                    factors.push(self.atom()?);
+                }
+                _ => break,
+            }
+        }
+        Ok(if factors.len() == 1 {
+            factors.pop().unwrap()
+        } else {
+            Product::new(factors).expr()
+        })
+    }
+    /// A factor after a multiplication sign, which can be negated like `x * -y`.
+    fn factor(&mut self) -> Result<Expression, ParseError> {
+        if self.next_token() == Some('-') {
+            self.bump();
+            Ok(Negation::new(self.nested(ParseError::TooDeep, Self::factor)?).expr())
+        } else {
+            self.atom()
+        }
+    }
+    fn atom(&mut self) -> Result<Expression, ParseError> {
+        match self.next_token() {
+            Some('(') => {
+                self.bump();
+                let inner = self.nested(ParseError::TooDeep, Self::sum)?;
+                if self.next_token() != Some(')') {
+                    return Err(self.unexpected());
+                }
+                self.bump();
+                Ok(inner)
+            }
+            Some(c) if c == '\\' || is_name_char(c) => {
+                let start = self.offset;
+                self.bump();
+                while self.peek().is_some_and(is_name_char) {
+                    self.bump();
+                }
+                let name = &self.input[start..self.offset];
+                self.constant(name)
+                    .ok_or_else(|| ParseError::UnknownLatex(name.to_owned()))
+            }
+            _ => Err(self.unexpected()),
+        }
+    }
+}
+
+/// Parses an infix expression, like `-(x + y) * z`.
+pub fn parse(input: &str) -> Result<Expression, ParseError> {
+    let mut parser = Parser::new(input);
+    let expression = parser.sum()?;
+    match parser.next_token() {
+        None => Ok(expression),
+        Some(_) => Err(parser.unexpected()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{constant::Constant, math, parser::MAX_DEPTH, sexpr::sexpr_print};
+
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let parsed = |input| sexpr_print(&parse(input).unwrap());
+        insta::assert_snapshot!(parsed("x + y * z"), @"(+ x (* y z))");
+        insta::assert_snapshot!(parsed("(x * y) * z"), @"(* (* x y) z)");
+        insta::assert_snapshot!(parsed("x - y - -z"), @"(+ x (- y) (- (- z)))");
+        insta::assert_snapshot!(parsed("-x * y + x * -y"), @"(+ (- (* x y)) (* x (- y)))");
+        insta::assert_snapshot!(parsed(r"2 x (\pi + π_2) × ρ"), @r"(* 2 x (+ \pi π_2) \rho)");
+        insta::assert_snapshot!(parsed("((x))"), @"x");
+
+        let Expression::Product(product) = parse("x * x").unwrap() else {
+            unreachable!()
+        };
+        assert_eq!(product.terms()[0].id(), product.terms()[1].id());
+
+        let error = |input: &str| parse(input).unwrap_err();
+        assert_eq!(error("(x + y"), ParseError::UnexpectedEnd);
+        assert_eq!(error("x + * y"), ParseError::Unexpected(4, '*'));
+        assert_eq!(error("x)"), ParseError::Unexpected(1, ')'));
+        assert_eq!(error(r"\foo"), ParseError::UnknownLatex(r"\foo".to_owned()));
+
+        let nested = |depth| format!("{}x{}", "(".repeat(depth), ")".repeat(depth));
+        assert!(parse(&nested(MAX_DEPTH)).is_ok());
+        assert_eq!(
+            error(&nested(MAX_DEPTH + 1)),
+            ParseError::TooDeep(MAX_DEPTH + 1)
+        );
+        assert_eq!(error(&nested(200_000)), ParseError::TooDeep(MAX_DEPTH + 1));
+        assert!(parse(&format!("{}x", "-".repeat(MAX_DEPTH))).is_ok());
+        assert_eq!(
+            error(&format!("x * {}x", "-".repeat(200_000))),
+            ParseError::TooDeep(4 + MAX_DEPTH + 1)
+        );
+    }
+
+    #[test]
+    fn test_parse_printed() {
+        let x = Constant::new("x");
+        let y = Constant::new("y");
+        let z = Constant::new("z");
+
+        let exp = math![(-(x + y)) + (z * ((-x) * (-y))) + ((x * y) * z) + (-(x * (-y)))].expr();
+        let printed = exp.to_string();
+        insta::assert_snapshot!(printed, @"-(x + y) + z * ((-x) * (-y)) + (x * y) * z - x * (-y)");
+        assert_eq!(sexpr_print(&parse(&printed).unwrap()), sexpr_print(&exp));
+    }
+}
@@ -0,0 +1,69 @@
+//! What the infix and s-expression parsers share.
+//! Each adds its grammar to [`Parser`] in its own module.
+
+use std::collections::{hash_map::Entry, HashMap};
+
+use crate::{
+    constant::{unicode_to_latex, Constant},
+    expression::{AsExpression, Expression},
+};
+
+/// How deep parentheses, negations and lists can be nested.
+/// Parsing recurses for each level, so this keeps deeply nested input from overflowing the stack.
+pub(crate) const MAX_DEPTH: usize = 256;
+
+pub(crate) struct Parser<'a> {
+    pub(crate) input: &'a str,
+    pub(crate) offset: usize,
+    /// Constants with the same name share a node, like they do when built with `math!`.
+    constants: HashMap<String, Constant>,
+    depth: usize,
+}
+
+impl<'a> Parser<'a> {
+    pub(crate) fn new(input: &'a str) -> Self {
+        Parser {
+            input,
+            offset: 0,
+            constants: HashMap::new(),
+            depth: 0,
+        }
+    }
+    pub(crate) fn peek(&self) -> Option<char> {
+        self.input[self.offset..].chars().next()
+    }
+    pub(crate) fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.offset += c.len_utf8();
+        Some(c)
+    }
+    pub(crate) fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.bump();
+        }
+    }
+    /// Parses something nested one level deeper, like the inside of parentheses.
+    /// Fails with `too_deep` at the current offset if that's more than [`MAX_DEPTH`] levels.
+    pub(crate) fn nested<E>(
+        &mut self,
+        too_deep: fn(usize) -> E,
+        parse: impl FnOnce(&mut Self) -> Result<Expression, E>,
+    ) -> Result<Expression, E> {
+        if self.depth == MAX_DEPTH {
+            return Err(too_deep(self.offset));
+        }
+        self.depth += 1;
+        let result = parse(self);
+        self.depth -= 1;
+        result
+    }
+    /// The constant with this name, or `None` if it's a LaTeX command that isn't supported.
+    pub(crate) fn constant(&mut self, name: &str) -> Option<Expression> {
+        let name = unicode_to_latex(name);
+        let constant = match self.constants.entry(name.to_owned()) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(Constant::try_new(name)?),
+        };
+        Some(constant.expr())
+    }
+}
@@ -1,14 +1,16 @@
 use crate::{
     annotated_expression::Annotation,
+    evaluate::{EvalError, Evaluable, Number},
     expression::{gen_id, Expression, ExpressionId, PRECEDENCE_PRODUCT},
     token_stream::TokenStream,
     tokens,
     traverse::Traversable,
-    PrintOpts, PrintTarget, Printable,
+    MultiplicationSign, Parentheses, PrintOpts, PrintTarget, Printable,
 };
 
+/// Terms multiplied together.
 #[derive(Clone)]
-pub(crate) struct Product {
+pub struct Product {
     terms: Vec<Expression>,
     id: ExpressionId,
 }
@@ -26,26 +28,40 @@ impl Product {
         &self.terms
     }
     #[inline]
-    pub(crate) fn id(&self) -> ExpressionId {
+    pub fn id(&self) -> ExpressionId {
         self.id
     }
 }
 
 impl Printable for Product {
-    fn print<'a>(&'a self, print_opts: &'a PrintOpts, annotations: &[Annotation]) -> TokenStream {
+    fn print<'a>(
+        &'a self,
+        target: PrintTarget,
+        print_opts: &'a PrintOpts,
+        annotations: &[Annotation],
+    ) -> TokenStream {
+        let times = print_opts.times(target);
         TokenStream::from_iter(Box::new(self.terms.iter().enumerate().flat_map(
-            |(i, term)| {
-                let inner = if term.precedence() <= PRECEDENCE_PRODUCT {
-                    term.print_with_parens(print_opts, annotations)
+            move |(i, term)| {
+                let needs_parens = match print_opts.parentheses {
+                    Parentheses::Faithful => term.precedence() <= PRECEDENCE_PRODUCT,
+                    // Without a multiplication sign, `x -y` would read as subtraction
+                    Parentheses::Minimal => match term {
+                        Expression::Sum(..) => true,
+                        Expression::Negation(..) => {
+                            i != 0
+                                && matches!(print_opts.multiplication, MultiplicationSign::Implicit)
+                        }
+                        _ => false,
+                    },
+                };
+                let inner = if needs_parens {
+                    term.print_with_parens(target, print_opts, annotations)
                 } else {
-                    term.print(print_opts, annotations)
+                    term.print(target, print_opts, annotations)
                 };
                 if i != 0 {
-                    if matches!(print_opts.target, PrintTarget::LaTex) {
-                        tokens![" ", inner]
-                    } else {
-                        tokens![" * ", inner]
-                    }
+                    tokens![std::iter::once(times.clone().into()), inner]
                 } else {
                     inner
                 }
@@ -54,6 +70,18 @@ impl Printable for Product {
     }
 }
 
+impl Evaluable for Product {
+    fn evaluate_with<V, F>(&self, lookup: &F) -> Result<V, EvalError>
+    where
+        V: Number,
+        F: Fn(&str) -> Result<V, EvalError>,
+    {
+        self.terms.iter().try_fold(V::one(), |product, term| {
+            Ok(product * term.evaluate_with(lookup)?)
+        })
+    }
+}
+
 impl Traversable for Product {
     fn child_iter<'a>(&'a self) -> Box<dyn Iterator<Item = &'a Expression> + 'a> {
         Box::new(self.terms.iter())
@@ -0,0 +1,233 @@
+//! A compact textual format for expressions, like `(+ x (- y) (* z (- x)))`.
+//! Unlike the infix printers it keeps the exact tree shape,
+//! so a sum directly inside a sum is written as `(+ a (+ b c))`, not `a + b + c`.
+
+use crate::{
+    expression::{AsExpression, Expression},
+    negation::Negation,
+    parser::Parser,
+    product::Product,
+    sum::Sum,
+};
+
+/// Writes the expression as an s-expression.
+/// Constants are written by their LaTeX name, quoted if they would be read as something else.
+pub(crate) fn write_sexpr(expr: &Expression, f: &mut impl std::fmt::Write) -> std::fmt::Result {
+    let (operator, terms) = match expr {
+        Expression::Constant(constant) => return write_name(constant.name(), f),
+        Expression::Sum(sum) => ('+', sum.terms()),
+        Expression::Product(product) => ('*', product.terms()),
+        Expression::Negation(negation) => ('-', std::slice::from_ref(negation.inner())),
+    };
+    write!(f, "({operator}")?;
+    for term in terms {
+        write!(f, " ")?;
+        write_sexpr(term, f)?;
+    }
+    write!(f, ")")
+}
+
+pub fn sexpr_print(expr: &Expression) -> String {
+    let mut out = String::new();
+    write_sexpr(expr, &mut out).unwrap();
+    out
+}
+
+fn is_operator(name: &str) -> bool {
+    matches!(name, "+" | "*" | "-")
+}
+
+fn is_delimiter(c: char) -> bool {
+    c.is_whitespace() || matches!(c, '(' | ')' | '"')
+}
+
+fn write_name(name: &str, f: &mut impl std::fmt::Write) -> std::fmt::Result {
+    if !name.is_empty() && !is_operator(name) && !name.contains(is_delimiter) {
+        return write!(f, "{name}");
+    }
+    write!(f, "\"")?;
+    for c in name.chars() {
+        if matches!(c, '"' | '\\') {
+            write!(f, "\\")?;
+        }
+        write!(f, "{c}")?;
+    }
+    write!(f, "\"")
+}
+
+/// Why [`parse_sexpr`] couldn't read an expression.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SexprError {
+    /// The input ended in the middle of an expression.
+    UnexpectedEnd,
+    /// An unexpected character, at the given byte offset.
+    Unexpected(usize, char),
+    /// An operator that isn't `+`, `*` or `-`, at the given byte offset.
+    UnknownOperator(usize, String),
+    /// A negation without exactly one operand, at the given byte offset.
+    NegationArity(usize),
+    /// A constant named with a LaTeX command that isn't supported.
+    UnknownLatex(String),
+    /// Lists nested too deeply, at the given byte offset.
+    TooDeep(usize),
+}
+
+impl std::fmt::Display for SexprError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SexprError::UnexpectedEnd => write!(f, "Unexpected end of input"),
+            SexprError::Unexpected(offset, c) => write!(f, "Unexpected {c:?} at {offset}"),
+            SexprError::UnknownOperator(offset, operator) => {
+                write!(f, "Unknown operator {operator:?} at {offset}")
+            }
+            SexprError::NegationArity(offset) => {
+                write!(f, "Negation at {offset} must have exactly one operand")
+            }
+            SexprError::UnknownLatex(name) => write!(f, "Unrecognized LaTeX code {name}"),
+            SexprError::TooDeep(offset) => write!(f, "Nested too deeply at {offset}"),
+        }
+    }
+}
+
+impl std::error::Error for SexprError {}
+
+impl Parser<'_> {
+    fn expression(&mut self) -> Result<Expression, SexprError> {
+        self.skip_whitespace();
+        let start = self.offset;
+        match self.peek() {
+            None => Err(SexprError::UnexpectedEnd),
+            Some('(') => {
+                self.bump();
+                self.nested(SexprError::TooDeep, |parser| parser.list(start))
+            }
+            Some(_) => {
+                let name = self.name()?;
+                self.constant(&name).ok_or(SexprError::UnknownLatex(name))
+            }
+        }
+    }
+    /// The operator and operands of a list starting at `start`, after its `(`.
+    fn list(&mut self, start: usize) -> Result<Expression, SexprError> {
+        self.skip_whitespace();
+        let operator_start = self.offset;
+        let operator = self.name()?;
+        let mut terms = vec![];
+        loop {
+            self.skip_whitespace();
+            if self.peek() == Some(')') {
+                self.bump();
+                break;
+            }
+            terms.push(self.expression()?);
+        }
+        match operator.as_str() {
+            "+" => Ok(Sum::new(terms).expr()),
+            "*" => Ok(Product::new(terms).expr()),
+            "-" => match <[Expression; 1]>::try_from(terms) {
+                Ok([inner]) => Ok(Negation::new(inner).expr()),
+                Err(_) => Err(SexprError::NegationArity(start)),
+            },
+            _ => Err(SexprError::UnknownOperator(operator_start, operator)),
+        }
+    }
+    /// A bare or quoted name.
+    fn name(&mut self) -> Result<String, SexprError> {
+        let start = self.offset;
+        match self.peek() {
+            None => Err(SexprError::UnexpectedEnd),
+            Some('"') => {
+                self.bump();
+                let mut name = String::new();
+                loop {
+                    match self.bump().ok_or(SexprError::UnexpectedEnd)? {
+                        '"' => return Ok(name),
+                        '\\' => name.push(self.bump().ok_or(SexprError::UnexpectedEnd)?),
+                        c => name.push(c),
+                    }
+                }
+            }
+            Some(c) if is_delimiter(c) => Err(SexprError::Unexpected(start, c)),
+            Some(_) => {
+                while self.peek().is_some_and(|c| !is_delimiter(c)) {
+                    self.bump();
+                }
+                Ok(self.input[start..self.offset].to_owned())
+            }
+        }
+    }
+}
+
+/// Reads an expression written by [`sexpr_print`].
+pub fn parse_sexpr(input: &str) -> Result<Expression, SexprError> {
+    let mut parser = Parser::new(input);
+    let expression = parser.expression()?;
+    parser.skip_whitespace();
+    match parser.peek() {
+        None => Ok(expression),
+        Some(c) => Err(SexprError::Unexpected(parser.offset, c)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{constant::Constant, math, parser::MAX_DEPTH};
+
+    use super::*;
+
+    #[test]
+    fn test_sexpr_print() {
+        let x = Constant::new("x");
+        let y = Constant::new("y");
+        let z = Constant::new("z");
+        let pi = Constant::new(r"\pi");
+
+        let exp = math![x + (-y) + (z * (-x))].expr();
+        insta::assert_snapshot!(sexpr_print(&exp), @"(+ x (- y) (* z (- x)))");
+
+        let exp = math![x + (y + (pi * z))].expr();
+        insta::assert_debug_snapshot!(exp, @r"(+ x (+ y (* \pi z)))");
+
+        let odd = Constant::new("a b");
+        let exp = math![odd * (-(x - y))].expr();
+        insta::assert_snapshot!(sexpr_print(&exp), @r#"(* "a b" (- (+ x (- y))))"#);
+    }
+
+    #[test]
+    fn test_parse_sexpr() {
+        let input = r#"(+ x (+ (- y) "a b") (* π \pi (- (* x))) (+))"#;
+        let exp = parse_sexpr(input).unwrap();
+        insta::assert_snapshot!(sexpr_print(&exp), @r#"(+ x (+ (- y) "a b") (* \pi \pi (- (* x))) (+))"#);
+        assert_eq!(
+            sexpr_print(&parse_sexpr(&sexpr_print(&exp)).unwrap()),
+            sexpr_print(&exp)
+        );
+
+        // Constants with the same name are the same node
+        let Expression::Product(product) = parse_sexpr(r"(* \pi π)").unwrap() else {
+            unreachable!()
+        };
+        assert_eq!(product.terms()[0].id(), product.terms()[1].id());
+
+        let error = |input: &str| parse_sexpr(input).unwrap_err();
+        assert_eq!(error("(+ x"), SexprError::UnexpectedEnd);
+        assert_eq!(error("(- x y)"), SexprError::NegationArity(0));
+        assert_eq!(
+            error("(/ x y)"),
+            SexprError::UnknownOperator(1, "/".to_owned())
+        );
+        assert_eq!(error("x)"), SexprError::Unexpected(1, ')'));
+        assert_eq!(error(r"\foo"), SexprError::UnknownLatex(r"\foo".to_owned()));
+
+        let nested = |depth| format!("{}x{}", "(- ".repeat(depth), ")".repeat(depth));
+        assert!(parse_sexpr(&nested(MAX_DEPTH)).is_ok());
+        assert_eq!(
+            error(&nested(MAX_DEPTH + 1)),
+            SexprError::TooDeep(3 * MAX_DEPTH + 1)
+        );
+        assert_eq!(
+            error(&format!("{}x", "(- ".repeat(100_000))),
+            SexprError::TooDeep(3 * MAX_DEPTH + 1)
+        );
+    }
+}
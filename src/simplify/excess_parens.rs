@@ -1,88 +1,94 @@
 use crate::{
-    annotated_expression::{AnnotatedExpression, Annotation},
     expression::{AsExpression, Expression},
     product::Product,
     step::Step,
     sum::Sum,
-    traverse::traverse,
 };
 
-pub(crate) fn simplify_excess_parens(expr: &Expression) -> Step {
-    let mut steps = vec![];
-    traverse(expr, |ctx| {
-        let snapshot_before = ctx.snapshot();
-        let mut annotations = vec![];
-        match ctx.expression {
-            Expression::Product(prod) => {
-                if prod
-                    .terms()
-                    .iter()
-                    .any(|term| matches!(term, Expression::Product(..)))
-                {
-                    let mut terms = vec![];
-                    for t in prod.terms() {
-                        match t {
-                            Expression::Product(product) => {
-                                annotations.push(Annotation {
-                                    target_id: product.id(),
-                                });
-                                terms.extend(product.terms().iter().cloned());
-                            }
-                            _ => terms.push(t.clone()),
-                        };
-                    }
-                    ctx.replace(Product::new(terms).expr());
-                    steps.push(Step {
-                        label: "Remove excess parentheses around product".to_owned().into(),
-                        annotated_expression: Some(AnnotatedExpression {
-                            expression: snapshot_before,
-                            annotations,
-                        }),
-                        substeps: vec![],
-                        result: ctx.snapshot(),
-                    });
-                }
-            }
-            Expression::Sum(prod) => {
-                if prod
+use super::{apply_rules, Change, Rewrite, Rule};
+
+/// Removes the parentheses around products inside a product, like `(x * y) * z`.
+pub(crate) const FLATTEN_PRODUCT: Rule = Rule {
+    name: "flatten-product",
+    label: "Remove excess parentheses around product",
+    rewrite: flatten_product,
+};
+
+/// Removes the parentheses around sums inside a sum, like `(x + y) + z`.
+pub(crate) const FLATTEN_SUM: Rule = Rule {
+    name: "flatten-sum",
+    label: "Remove excess parentheses around sum",
+    rewrite: flatten_sum,
+};
+
+fn flatten_product(expr: &Expression) -> Option<Rewrite> {
+    let Expression::Product(prod) = expr else {
+        return None;
+    };
+    if !prod
+        .terms()
+        .iter()
+        .any(|term| matches!(term, Expression::Product(..)))
+    {
+        return None;
+    }
+    let mut changes = vec![];
+    let mut terms = vec![];
+    for t in prod.terms() {
+        match t {
+            Expression::Product(product) => {
+                let spliced: Vec<_> = product
                     .terms()
                     .iter()
-                    .any(|term| matches!(term, Expression::Sum(..)))
-                {
-                    let mut terms = vec![];
-                    for t in prod.terms() {
-                        match t {
-                            Expression::Sum(sum) => {
-                                annotations.push(Annotation {
-                                    target_id: sum.id(),
-                                });
-                                terms.extend(sum.terms().iter().cloned());
-                            }
-                            _ => terms.push(t.clone()),
-                        };
-                    }
-                    ctx.replace(Sum::new(terms).expr());
-                    steps.push(Step {
-                        label: "Remove excess parentheses around sum".to_owned().into(),
-                        annotated_expression: Some(AnnotatedExpression {
-                            expression: snapshot_before,
-                            annotations,
-                        }),
-                        substeps: vec![],
-                        result: ctx.snapshot(),
-                    });
-                }
+                    .map(Expression::with_new_id)
+                    .collect();
+                changes.push(Change::new(t, &spliced));
+                terms.extend(spliced);
             }
-            _ => {}
-        }
-    });
+            _ => terms.push(t.clone()),
+        };
+    }
+    Some(Rewrite {
+        replacement: Product::new(terms).expr(),
+        changes,
+    })
+}
 
-    Step {
-        label: "Simplify excess parentheses".to_owned().into(),
-        annotated_expression: None,
-        result: steps.last().unwrap().result.clone(),
-        substeps: steps,
+fn flatten_sum(expr: &Expression) -> Option<Rewrite> {
+    let Expression::Sum(sum) = expr else {
+        return None;
+    };
+    if !sum
+        .terms()
+        .iter()
+        .any(|term| matches!(term, Expression::Sum(..)))
+    {
+        return None;
+    }
+    let mut changes = vec![];
+    let mut terms = vec![];
+    for t in sum.terms() {
+        match t {
+            Expression::Sum(sum) => {
+                let spliced: Vec<_> = sum.terms().iter().map(Expression::with_new_id).collect();
+                changes.push(Change::new(t, &spliced));
+                terms.extend(spliced);
+            }
+            _ => terms.push(t.clone()),
+        };
     }
+    Some(Rewrite {
+        replacement: Sum::new(terms).expr(),
+        changes,
+    })
+}
+
+pub fn simplify_excess_parens(expr: &Expression) -> Step {
+    apply_rules(
+        expr,
+        &[&FLATTEN_PRODUCT, &FLATTEN_SUM],
+        "Simplify excess parentheses",
+    )
 }
 
 #[cfg(test)]
@@ -104,6 +110,7 @@ mod tests {
             (x * y) * z
              ^^^^^
             x * y * z
+            ~   ~
           x * y * z
         "###);
 
@@ -115,6 +122,7 @@ mod tests {
             x * (y * z)
                  ^^^^^
             x * y * z
+                ~   ~
           x * y * z
         "###);
 
@@ -125,15 +133,22 @@ mod tests {
           Remove excess parentheses around product
             ((x * y) * (x * y)) * (z * (z * x * x))
               ^^^^^     ^^^^^
+              1         2
             (x * y * x * y) * (z * (z * x * x))
+             ~   ~   ~   ~
+             1   1   2   2
           Remove excess parentheses around product
             (x * y * x * y) * (z * (z * x * x))
                                     ^^^^^^^^^
             (x * y * x * y) * (z * z * x * x)
+                                   ~   ~   ~
           Remove excess parentheses around product
             (x * y * x * y) * (z * z * x * x)
              ^^^^^^^^^^^^^     ^^^^^^^^^^^^^
+             1                 2
             x * y * x * y * z * z * x * x
+            ~   ~   ~   ~   ~   ~   ~   ~
+            1   1   1   1   2   2   2   2
           x * y * x * y * z * z * x * x
         "###);
 
@@ -145,11 +160,20 @@ mod tests {
             ((x + y) + y) * (x * y) * (z * x + y)
               ^^^^^
             (x + y + y) * (x * y) * (z * x + y)
+             ~   ~
           Remove excess parentheses around product
             (x + y + y) * (x * y) * (z * x + y)
                            ^^^^^
             (x + y + y) * x * y * (z * x + y)
+                          ~   ~
           (x + y + y) * x * y * (z * x + y)
         "###);
+
+        // Already simplified, so there are no steps
+        let exp = math![(x * y) + z].expr();
+        insta::assert_display_snapshot!(simplify_excess_parens(&exp), @r###"
+        Simplify excess parentheses
+          x * y + z
+        "###);
     }
 }
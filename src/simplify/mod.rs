@@ -1,2 +1,227 @@
+use crate::{
+    annotated_expression::{AnnotatedExpression, Annotation, AnnotationKind},
+    expression::Expression,
+    step::Step,
+    traverse::{traverse, Traversable},
+};
+
 mod excess_parens;
-pub(crate) use excess_parens::simplify_excess_parens;
+pub use excess_parens::simplify_excess_parens;
+
+/// Simplifies the expression, returning the steps taken.
+/// The simplified expression is the [`Step::result`].
+pub fn simplify(expr: &Expression) -> Step {
+    simplify_excess_parens(expr)
+}
+
+/// What a rule replaces an expression with.
+pub(crate) struct Rewrite {
+    pub(crate) replacement: Expression,
+    pub(crate) changes: Vec<Change>,
+}
+
+/// A part of the original expression that a rule changed, and the parts of the replacement
+/// that took its place.
+pub(crate) struct Change {
+    pub(crate) before: Annotation,
+    pub(crate) after: Vec<Annotation>,
+}
+
+impl Change {
+    /// Highlights `before`, and each of `after` as [`AnnotationKind::Added`].
+    /// The nodes in `after` should only be used in the replacement, see [`Expression::with_new_id`].
+    pub(crate) fn new(before: &Expression, after: &[Expression]) -> Self {
+        Self {
+            before: Annotation::new(before),
+            after: after
+                .iter()
+                .map(|expr| Annotation::new(expr).with_kind(AnnotationKind::Added))
+                .collect(),
+        }
+    }
+}
+
+/// The annotations on the original expression, and on the result,
+/// each with the index of the change it belongs to.
+/// When there's more than one change, they're also labeled with their number,
+/// so it's clear which parts of the result replaced which parts of the original.
+fn split_changes(changes: Vec<Change>) -> (Vec<Annotation>, Vec<Annotation>) {
+    let numbered = changes.len() > 1;
+    let pair = |annotation: Annotation, i: usize| {
+        let annotation = annotation.with_change(i);
+        if numbered {
+            annotation.with_label((i + 1).to_string())
+        } else {
+            annotation
+        }
+    };
+    let mut annotations = vec![];
+    let mut result_annotations = vec![];
+    for (i, change) in changes.into_iter().enumerate() {
+        annotations.push(pair(change.before, i));
+        result_annotations.extend(change.after.into_iter().map(|after| pair(after, i)));
+    }
+    (annotations, result_annotations)
+}
+
+/// A simplification that can be applied by name to one part of an expression.
+pub struct Rule {
+    /// What the rule is called when choosing it by name, like `flatten-sum`.
+    pub name: &'static str,
+    /// The label of the steps it makes.
+    pub label: &'static str,
+    rewrite: fn(&Expression) -> Option<Rewrite>,
+}
+
+/// Every rule that can be applied by name.
+pub const RULES: &[Rule] = &[excess_parens::FLATTEN_PRODUCT, excess_parens::FLATTEN_SUM];
+
+/// Looks up a rule in [`RULES`].
+pub fn find_rule(name: &str) -> Option<&'static Rule> {
+    RULES.iter().find(|rule| rule.name == name)
+}
+
+/// Simplifies the expression with only the given rules, trying them in order on each part.
+pub fn simplify_with(expr: &Expression, rules: &[&Rule]) -> Step {
+    apply_rules(expr, rules, "Simplify")
+}
+
+/// Applies the first of `rules` that matches to each part of `expr`, one step for each.
+pub(crate) fn apply_rules(expr: &Expression, rules: &[&Rule], label: &str) -> Step {
+    let mut steps = vec![];
+    traverse(expr, |ctx| {
+        let snapshot_before = ctx.snapshot();
+        for rule in rules {
+            let Some(Rewrite {
+                replacement,
+                changes,
+            }) = (rule.rewrite)(ctx.expression)
+            else {
+                continue;
+            };
+            let (annotations, result_annotations) = split_changes(changes);
+            ctx.replace(replacement);
+            steps.push(Step {
+                label: rule.label.to_owned().into(),
+                annotated_expression: Some(AnnotatedExpression {
+                    expression: snapshot_before,
+                    annotations,
+                }),
+                substeps: vec![],
+                result: ctx.snapshot(),
+                result_annotations,
+            });
+            break;
+        }
+    });
+
+    Step {
+        label: label.to_owned().into(),
+        annotated_expression: None,
+        result: steps
+            .last()
+            .map_or_else(|| expr.clone(), |step| step.result.clone()),
+        result_annotations: vec![],
+        substeps: steps,
+    }
+}
+
+impl Rule {
+    /// Applies the rule to the part of `expr` at `path`, see [`Expression::subexpression`].
+    /// Returns `None` if there is nothing at the path, or the rule doesn't apply to it.
+    pub fn apply_at(&self, expr: &Expression, path: &[usize]) -> Option<Step> {
+        let Rewrite {
+            replacement,
+            changes,
+        } = (self.rewrite)(expr.subexpression(path)?)?;
+        let (annotations, result_annotations) = split_changes(changes);
+        Some(Step {
+            label: Some(self.label.to_owned()),
+            annotated_expression: Some(AnnotatedExpression {
+                expression: expr.clone(),
+                annotations,
+            }),
+            substeps: vec![],
+            result: replace_at(expr, path, replacement),
+            result_annotations,
+        })
+    }
+}
+
+/// Replaces the part of `expr` at `path`, rebuilding the expressions containing it.
+fn replace_at(expr: &Expression, path: &[usize], replacement: Expression) -> Expression {
+    let Some((&index, rest)) = path.split_first() else {
+        return replacement;
+    };
+    let mut replacement = Some(replacement);
+    let children = expr
+        .child_iter()
+        .enumerate()
+        .map(|(i, child)| {
+            if i == index {
+                replace_at(child, rest, replacement.take().unwrap())
+            } else {
+                child.clone()
+            }
+        })
+        .collect();
+    Expression::from_children(expr, children)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{constant::Constant, expression::AsExpression, math};
+
+    #[test]
+    fn test_apply_rule_at() {
+        let x = Constant::new("x");
+        let y = Constant::new("y");
+        let z = Constant::new("z");
+
+        let exp = math![((x * y) * z) + ((x + y) + z)].expr();
+        let flatten_product = find_rule("flatten-product").unwrap();
+        insta::assert_display_snapshot!(flatten_product.apply_at(&exp, &[0]).unwrap(), @r###"
+        Remove excess parentheses around product
+          (x * y) * z + ((x + y) + z)
+           ^^^^^
+          x * y * z + ((x + y) + z)
+          ~   ~
+        "###);
+        assert!(flatten_product.apply_at(&exp, &[]).is_none());
+        assert!(flatten_product.apply_at(&exp, &[1]).is_none());
+        assert!(flatten_product.apply_at(&exp, &[2]).is_none());
+
+        let flatten_sum = find_rule("flatten-sum").unwrap();
+        insta::assert_display_snapshot!(flatten_sum.apply_at(&exp, &[1]).unwrap(), @r###"
+        Remove excess parentheses around sum
+          (x * y) * z + ((x + y) + z)
+                          ^^^^^
+          (x * y) * z + (x + y + z)
+                         ~   ~
+        "###);
+    }
+
+    #[test]
+    fn test_simplify_with() {
+        let x = Constant::new("x");
+        let y = Constant::new("y");
+        let z = Constant::new("z");
+
+        let exp = math![((x * y) * z) + ((x + y) + z)].expr();
+        insta::assert_display_snapshot!(simplify_with(&exp, &[find_rule("flatten-sum").unwrap()]), @r###"
+        Simplify
+          Remove excess parentheses around sum
+            (x * y) * z + ((x + y) + z)
+                            ^^^^^
+            (x * y) * z + (x + y + z)
+                           ~   ~
+          Remove excess parentheses around sum
+            (x * y) * z + (x + y + z)
+                           ^^^^^^^^^
+            (x * y) * z + x + y + z
+                          ~   ~   ~
+          (x * y) * z + x + y + z
+        "###);
+    }
+}
@@ -1,34 +1,134 @@
 use crate::{
-    annotated_expression::AnnotatedExpression,
+    annotated_expression::{AnnotatedExpression, Annotation},
     expression::{Expression, DEFAULT_PRINT_OPTS},
-    token_stream::latex_print,
+    token_stream::{
+        escape_typst, html_print, latex_print_wrapped, math_print_wrapped, mathml_print,
+        typst_print,
+    },
     PrintOpts, PrintTarget, Printable,
 };
 
-pub(crate) struct Step {
-    pub(crate) label: Option<String>,
-    pub(crate) annotated_expression: Option<AnnotatedExpression>,
-    pub(crate) substeps: Vec<Step>,
-    pub(crate) result: Expression,
+/// A step in simplifying an expression, and the steps it was made of.
+pub struct Step {
+    pub label: Option<String>,
+    pub annotated_expression: Option<AnnotatedExpression>,
+    pub substeps: Vec<Step>,
+    pub result: Expression,
+    /// Highlights where the changes landed in `result`.
+    /// They belong to the same [`Annotation::change`] as the parts of `annotated_expression` they replaced.
+    pub result_annotations: Vec<Annotation>,
 }
 
+#[cfg(feature = "katex")]
 fn latex_to_html(input: &str) -> String {
     let opts = katex::Opts::builder().trust(true).build().unwrap();
     katex::render_with_opts(input, opts).unwrap()
 }
 
+/// Whether expressions printed for the target are rendered to HTML through KaTeX,
+/// so the page needs KaTeX's stylesheet and fonts.
+pub(crate) fn uses_katex(target: PrintTarget) -> bool {
+    cfg!(feature = "katex") && matches!(target, PrintTarget::LaTex | PrintTarget::Typst)
+}
+
+/// Renders the expression to HTML: MathML natively, MathPrint as styled text,
+/// and LaTeX through KaTeX.
+/// Browsers can't display Typst, so it's rendered the same way as LaTeX.
+fn expression_to_html(
+    expression: &Expression,
+    annotations: &[Annotation],
+    target: PrintTarget,
+    print_opts: &PrintOpts,
+) -> String {
+    match target {
+        PrintTarget::MathML => mathml_print(&expression.print(target, print_opts, annotations)),
+        PrintTarget::MathPrint => html_print(&expression.print(target, print_opts, annotations)),
+        PrintTarget::LaTex | PrintTarget::Typst => {
+            latex_expression_to_html(expression, annotations, print_opts)
+        }
+    }
+}
+
+#[cfg(feature = "katex")]
+fn latex_expression_to_html(
+    expression: &Expression,
+    annotations: &[Annotation],
+    print_opts: &PrintOpts,
+) -> String {
+    let tokens = expression.print(PrintTarget::LaTex, print_opts, annotations);
+    latex_to_html(&latex_print_wrapped(&tokens, print_opts.line_width))
+}
+
+/// Without KaTeX, LaTeX is shown as MathML instead.
+#[cfg(not(feature = "katex"))]
+fn latex_expression_to_html(
+    expression: &Expression,
+    annotations: &[Annotation],
+    print_opts: &PrintOpts,
+) -> String {
+    mathml_print(&expression.print(PrintTarget::MathML, print_opts, annotations))
+}
+
+/// Indents every line by two spaces.
+fn indent(text: &str) -> String {
+    text.trim_start()
+        .lines()
+        .enumerate()
+        .map(|(i, line)| {
+            if i == 0 {
+                format!("  {line}")
+            } else {
+                format!("\n  {line}")
+            }
+        })
+        .collect()
+}
+
+/// Options for printing a step and its substeps.
+/// The expression each step starts from can be printed differently from its result,
+/// for example with faithful parentheses so it's clear which nesting is about to change,
+/// followed by a result with only the parentheses it needs.
+#[derive(Clone, Copy)]
+pub struct StepPrintOpts {
+    pub before: PrintOpts,
+    pub result: PrintOpts,
+}
+
+impl StepPrintOpts {
+    /// Prints every expression with the same options.
+    pub const fn uniform(print_opts: PrintOpts) -> Self {
+        Self {
+            before: print_opts,
+            result: print_opts,
+        }
+    }
+}
+
 impl Step {
-    pub(crate) fn html_print<'a>(&'a self, print_opts: &'a PrintOpts) -> String {
+    /// Prints the steps as HTML, with expressions as MathML, styled MathPrint text,
+    /// or LaTeX rendered by KaTeX.
+    pub fn html_print<'a>(&'a self, target: PrintTarget, print_opts: &'a StepPrintOpts) -> String {
         let mut inner = String::new();
         if let Some(annotated_expression) = &self.annotated_expression {
-            inner.push_str(&latex_to_html(&latex_print(
-                &annotated_expression.print(print_opts),
-            )));
+            inner.push_str(&expression_to_html(
+                &annotated_expression.expression,
+                &annotated_expression.annotations,
+                target,
+                &print_opts.before,
+            ));
         }
         for step in &self.substeps {
-            inner.push_str(&format!("\n<div>{}</div>", step.html_print(print_opts)));
+            inner.push_str(&format!(
+                "\n<div>{}</div>",
+                step.html_print(target, print_opts)
+            ));
         }
-        let result = latex_to_html(&self.result.latex());
+        let result = expression_to_html(
+            &self.result,
+            &self.result_annotations,
+            target,
+            &print_opts.result,
+        );
         if let Some(label) = &self.label {
             format!(
                 r###"
@@ -42,54 +142,125 @@ impl Step {
                 </details>
                 <div>{result}</div>
                 "###,
-                inner
-                    .trim_start()
-                    .lines()
-                    .enumerate()
-                    .map(|(i, line)| if i == 0 {
-                        format!("  {line}")
-                    } else {
-                        format!("\n  {line}")
-                    })
-                    .collect::<String>()
+                indent(&inner)
             )
         } else {
-            todo!()
+            format!(
+                r###"
+                <div class="substeps">
+                {}
+                </div>
+                <div>{result}</div>
+                "###,
+                indent(&inner)
+            )
         }
     }
-    // TODO: PrintOpts shouldn't include format in it
-    fn math_print<'a>(&'a self, print_opts: &'a PrintOpts) -> String {
+    pub fn math_print<'a>(&'a self, print_opts: &'a StepPrintOpts) -> String {
         let mut inner = String::new();
         if let Some(annotated_expression) = &self.annotated_expression {
-            inner.push_str(&format!("\n{}", annotated_expression));
+            inner.push_str(&format!(
+                "\n{}",
+                math_print_wrapped(
+                    &annotated_expression.print(PrintTarget::MathPrint, &print_opts.before),
+                    print_opts.before.line_width
+                )
+            ));
         }
         for step in &self.substeps {
             inner.push_str(&format!("\n{}", step.math_print(print_opts)));
         }
-        inner.push_str(&format!("\n{}", self.result));
+        inner.push_str(&format!(
+            "\n{}",
+            math_print_wrapped(
+                &self.result.print(
+                    PrintTarget::MathPrint,
+                    &print_opts.result,
+                    &self.result_annotations
+                ),
+                print_opts.result.line_width
+            )
+        ));
         if let Some(label) = &self.label {
-            format!(
-                "{label}\n{}",
-                inner
-                    .trim_start()
-                    .lines()
-                    .enumerate()
-                    .map(|(i, line)| if i == 0 {
-                        format!("  {line}")
-                    } else {
-                        format!("\n  {line}")
-                    })
-                    .collect::<String>()
+            format!("{label}\n{}", indent(&inner))
+        } else {
+            inner
+        }
+    }
+    /// Like [`Step::math_print`], but with expressions as LaTeX for KaTeX.
+    pub fn latex_print(&self, print_opts: &StepPrintOpts) -> String {
+        let mut inner = String::new();
+        if let Some(annotated_expression) = &self.annotated_expression {
+            inner.push_str(&format!(
+                "\n{}",
+                latex_print_wrapped(
+                    &annotated_expression.print(PrintTarget::LaTex, &print_opts.before),
+                    print_opts.before.line_width
+                )
+            ));
+        }
+        for step in &self.substeps {
+            inner.push_str(&format!("\n{}", step.latex_print(print_opts)));
+        }
+        inner.push_str(&format!(
+            "\n{}",
+            latex_print_wrapped(
+                &self.result.print(
+                    PrintTarget::LaTex,
+                    &print_opts.result,
+                    &self.result_annotations
+                ),
+                print_opts.result.line_width
             )
+        ));
+        if let Some(label) = &self.label {
+            format!("{label}\n{}", indent(&inner))
         } else {
             inner
         }
     }
+    /// Prints the step as nested Typst lists, with each expression as display math.
+    pub fn typst_print(&self, print_opts: &StepPrintOpts) -> String {
+        let mut inner = String::new();
+        if let Some(annotated_expression) = &self.annotated_expression {
+            inner.push_str(&format!(
+                "\n$ {} $",
+                typst_print(&annotated_expression.print(PrintTarget::Typst, &print_opts.before))
+            ));
+        }
+        for step in &self.substeps {
+            inner.push_str(&format!("\n{}", step.typst_print(print_opts)));
+        }
+        inner.push_str(&format!(
+            "\n$ {} $",
+            typst_print(&self.result.print(
+                PrintTarget::Typst,
+                &print_opts.result,
+                &self.result_annotations
+            ))
+        ));
+        if let Some(label) = &self.label {
+            format!("- {}\n{}", escape_typst(label), indent(&inner))
+        } else {
+            inner.trim_start().to_owned()
+        }
+    }
+    /// A standalone Typst document containing the whole derivation.
+    pub fn typst_document(&self, print_opts: &StepPrintOpts) -> String {
+        format!(
+            "#set page(width: auto, height: auto, margin: 1cm)\n\n{}\n",
+            self.typst_print(print_opts)
+        )
+    }
 }
 
 impl std::fmt::Display for Step {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.math_print(&DEFAULT_PRINT_OPTS))
+        write!(
+            f,
+            "{}",
+            self.math_print(&StepPrintOpts::uniform(DEFAULT_PRINT_OPTS))
+        )
     }
 }
 
@@ -98,8 +269,8 @@ mod tests {
     use insta::assert_display_snapshot;
 
     use crate::{
-        annotated_expression::Annotation, constant::Constant, expression::AsExpression, math,
-        sum::Sum,
+        annotated_expression::AnnotationKind, constant::Constant, expression::AsExpression, math,
+        simplify::simplify_excess_parens, sum::Sum, Parentheses,
     };
 
     use super::*;
@@ -138,10 +309,14 @@ mod tests {
                         expression: input_exp.clone().expr(),
                         annotations: vec![Annotation {
                             target_id: inner_2_id,
+                            kind: AnnotationKind::Focus,
+                            label: None,
+                            change: None,
                         }],
                     }),
                     substeps: vec![],
                     result: Sum::new(vec![inner_1, math![(-a)].expr(), math![(-b)].expr()]).expr(),
+                    result_annotations: vec![],
                 },
                 Step {
                     label: label("Remove unneeded parentheses"),
@@ -149,13 +324,18 @@ mod tests {
                         expression: input_exp.expr(),
                         annotations: vec![Annotation {
                             target_id: inner_1_id,
+                            kind: AnnotationKind::Focus,
+                            label: None,
+                            change: None,
                         }],
                     }),
                     substeps: vec![],
                     result: final_exp.clone(),
+                    result_annotations: vec![],
                 },
             ],
             result: final_exp,
+            result_annotations: vec![],
         };
         assert_display_snapshot!(steps, @r###"
         Simplify parentheses and negative signs:
@@ -170,4 +350,144 @@ mod tests {
           x + y - a - b
         "###);
     }
+
+    #[test]
+    fn test_html_printing_mathml() {
+        let x = Constant::new("x");
+        let y = Constant::new("y");
+        let z = Constant::new("z");
+
+        let steps = simplify_excess_parens(&math![(x * y) * z].expr());
+        insta::assert_snapshot!(steps.html_print(
+            PrintTarget::MathML,
+            &StepPrintOpts::uniform(PrintOpts::default_for(PrintTarget::MathML))
+        ), @r###"
+        <details>
+            <summary>
+                Simplify excess parentheses
+            </summary>
+            <div class="substeps">
+              <div>
+          <details>
+              <summary>
+                  Remove excess parentheses around product
+              </summary>
+              <div class="substeps">
+                <math><mrow><mo>(</mo><mrow class="hl"><mi>x</mi><mo>&#x2062;</mo><mi>y</mi></mrow><mo>)</mo></mrow><mo>&#x2062;</mo><mi>z</mi></math>
+              </div>
+          </details>
+          <div><math><mrow class="hl-added"><mi>x</mi></mrow><mo>&#x2062;</mo><mrow class="hl-added"><mi>y</mi></mrow><mo>&#x2062;</mo><mi>z</mi></math></div>
+          </div>
+            </div>
+        </details>
+        <div><math><mi>x</mi><mo>&#x2062;</mo><mi>y</mi><mo>&#x2062;</mo><mi>z</mi></math></div>
+        "###);
+    }
+
+    #[test]
+    fn test_latex_printing_steps() {
+        let x = Constant::new("x");
+        let y = Constant::new("y");
+        let z = Constant::new("z");
+
+        let steps = simplify_excess_parens(&math![(x * y) * z].expr());
+        insta::assert_snapshot!(steps.latex_print(
+            &StepPrintOpts::uniform(PrintOpts::default_for(PrintTarget::LaTex))
+        ), @r###"
+        Simplify excess parentheses
+          Remove excess parentheses around product
+            \left(\htmlClass{hl}{x y}\right) z
+            \htmlClass{hl-added}{x} \htmlClass{hl-added}{y} z
+          x y z
+        "###);
+    }
+
+    #[test]
+    fn test_typst_document() {
+        let x = Constant::new("x");
+        let y = Constant::new("y");
+        let z = Constant::new("z");
+
+        let steps = simplify_excess_parens(&math![(x * y) * (z * (x * y))].expr());
+        insta::assert_snapshot!(steps.typst_document(&StepPrintOpts::uniform(PrintOpts::default_for(
+            PrintTarget::Typst
+        ))), @r###"
+        #set page(width: auto, height: auto, margin: 1cm)
+
+        - Simplify excess parentheses
+          - Remove excess parentheses around product
+            $ (x y) (z (#highlight[$x y$])) $
+            $ (x y) (z #highlight(fill: rgb("#9be9a8"))[$x$] #highlight(fill: rgb("#9be9a8"))[$y$]) $
+          - Remove excess parentheses around product
+            $ (underbrace(#highlight[$x y$], "1")) (underbrace(#highlight[$z x y$], "2")) $
+            $ underbrace(#highlight(fill: rgb("#9be9a8"))[$x$], "1") underbrace(#highlight(fill: rgb("#9be9a8"))[$y$], "1") underbrace(#highlight(fill: rgb("#9be9a8"))[$z$], "2") underbrace(#highlight(fill: rgb("#9be9a8"))[$x$], "2") underbrace(#highlight(fill: rgb("#9be9a8"))[$y$], "2") $
+          $ x y z x y $
+        "###);
+    }
+
+    #[test]
+    fn test_minimal_parentheses_in_results() {
+        let x = Constant::new("x");
+        let y = Constant::new("y");
+        let z = Constant::new("z");
+
+        let steps = simplify_excess_parens(&math![((x + y) + y) * (x * y) * ((z * x) + y)].expr());
+        let print_opts = StepPrintOpts {
+            before: DEFAULT_PRINT_OPTS,
+            result: PrintOpts {
+                parentheses: Parentheses::Minimal,
+                ..DEFAULT_PRINT_OPTS
+            },
+        };
+        insta::assert_snapshot!(steps.math_print(&print_opts), @r###"
+        Simplify excess parentheses
+          Remove excess parentheses around sum
+            ((x + y) + y) * (x * y) * (z * x + y)
+              ^^^^^
+            (x + y + y) * x * y * (z * x + y)
+             ~   ~
+          Remove excess parentheses around product
+            (x + y + y) * (x * y) * (z * x + y)
+                           ^^^^^
+            (x + y + y) * x * y * (z * x + y)
+                          ~   ~
+          (x + y + y) * x * y * (z * x + y)
+        "###);
+    }
+
+    #[test]
+    fn test_html_printing_text() {
+        let x = Constant::new("x");
+        let y = Constant::new("y");
+        let z = Constant::new("z");
+
+        let steps = simplify_excess_parens(&math![(x * y) * z].expr());
+        let print_opts = StepPrintOpts::uniform(PrintOpts::default_for(PrintTarget::MathPrint));
+        insta::assert_snapshot!(steps.substeps[0].html_print(PrintTarget::MathPrint, &print_opts), @r###"
+        <details>
+            <summary>
+                Remove excess parentheses around product
+            </summary>
+            <div class="substeps">
+              <span class="expression">(<span class="hl">x * y</span>) * z</span>
+            </div>
+        </details>
+        <div><span class="expression"><span class="hl-added">x</span> * <span class="hl-added">y</span> * z</span></div>
+        "###);
+
+        let mut unlabeled = simplify_excess_parens(&math![(x * y) * z].expr());
+        unlabeled.label = None;
+        unlabeled.substeps[0].label = None;
+        insta::assert_snapshot!(unlabeled.html_print(PrintTarget::MathPrint, &print_opts), @r###"
+        <div class="substeps">
+          <div>
+          <div class="substeps">
+            <span class="expression">(<span class="hl">x * y</span>) * z</span>
+          </div>
+          <div><span class="expression"><span class="hl-added">x</span> * <span class="hl-added">y</span> * z</span></div>
+          </div>
+        </div>
+        <div><span class="expression">x * y * z</span></div>
+        "###);
+    }
 }
@@ -1,14 +1,16 @@
 use crate::{
     annotated_expression::Annotation,
-    expression::{gen_id, Expression, ExpressionId, PRECEDENCE_SUM},
-    token_stream::TokenStream,
+    evaluate::{EvalError, Evaluable, Number},
+    expression::{annotate, gen_id, Expression, ExpressionId, PRECEDENCE_SUM},
+    token_stream::{MathPrintToken, TokenStream},
     tokens,
     traverse::Traversable,
-    PrintOpts, PrintTarget, Printable,
+    Parentheses, PrintOpts, PrintTarget, Printable,
 };
 
+/// Terms added together. Subtraction is a [`Negation`](crate::Negation) term.
 #[derive(Clone)]
-pub(crate) struct Sum {
+pub struct Sum {
     terms: Vec<Expression>,
     id: ExpressionId,
 }
@@ -27,40 +29,72 @@ impl Sum {
     }
 
     #[inline]
-    pub(crate) fn id(&self) -> ExpressionId {
+    pub fn id(&self) -> ExpressionId {
         self.id
     }
 }
 
 impl Printable for Sum {
-    fn print<'a>(&'a self, print_opts: &'a PrintOpts, annotations: &[Annotation]) -> TokenStream {
-        let is_latex = matches!(print_opts.target, PrintTarget::LaTex);
+    fn print<'a>(
+        &'a self,
+        target: PrintTarget,
+        print_opts: &'a PrintOpts,
+        annotations: &[Annotation],
+    ) -> TokenStream {
+        let plus = print_opts.plus(target);
+        let minus = print_opts.minus(target);
+        let minimal = matches!(print_opts.parentheses, Parentheses::Minimal);
         TokenStream::from_iter(Box::new(self.terms.iter().enumerate().flat_map(
-            |(i, term)| {
+            move |(i, term)| {
                 if let Expression::Negation(neg) = term {
                     let inner = neg.inner();
-                    let inner_printed = if inner.precedence() <= PRECEDENCE_SUM {
-                        inner.print_with_parens(print_opts, annotations)
+                    let needs_parens = if minimal {
+                        matches!(inner, Expression::Sum(..) | Expression::Negation(..))
                     } else {
-                        inner.print(print_opts, annotations)
+                        inner.precedence() <= PRECEDENCE_SUM
                     };
-                    if i == 0 || is_latex {
-                        tokens!["-", inner_printed]
+                    let inner_printed = if needs_parens {
+                        inner.print_with_parens(target, print_opts, annotations)
                     } else {
-                        tokens![" - ", inner_printed]
+                        inner.print(target, print_opts, annotations)
+                    };
+                    // The negation is printed here rather than by itself,
+                    // so its annotations need to be added here too
+                    if i == 0 {
+                        annotate(
+                            neg.id(),
+                            annotations,
+                            tokens![
+                                std::iter::once(print_opts.negative(target).into()),
+                                inner_printed
+                            ],
+                        )
+                    } else {
+                        tokens![
+                            std::iter::once(MathPrintToken::BreakPoint),
+                            annotate(
+                                neg.id(),
+                                annotations,
+                                tokens![std::iter::once(minus.clone().into()), inner_printed],
+                            )
+                        ]
                     }
                 } else {
-                    let inner_printed = if term.precedence() <= PRECEDENCE_SUM {
-                        term.print_with_parens(print_opts, annotations)
+                    // Sums are associative, so nested sums never need parentheses for their meaning
+                    let needs_parens = !minimal && term.precedence() <= PRECEDENCE_SUM;
+                    let inner_printed = if needs_parens {
+                        term.print_with_parens(target, print_opts, annotations)
                     } else {
-                        term.print(print_opts, annotations)
+                        term.print(target, print_opts, annotations)
                     };
                     if i == 0 {
                         inner_printed
-                    } else if is_latex {
-                        tokens!["+", inner_printed]
                     } else {
-                        tokens![" + ", inner_printed]
+                        tokens![
+                            std::iter::once(MathPrintToken::BreakPoint),
+                            std::iter::once(plus.clone().into()),
+                            inner_printed
+                        ]
                     }
                 }
             },
@@ -68,6 +102,18 @@ impl Printable for Sum {
     }
 }
 
+impl Evaluable for Sum {
+    fn evaluate_with<V, F>(&self, lookup: &F) -> Result<V, EvalError>
+    where
+        V: Number,
+        F: Fn(&str) -> Result<V, EvalError>,
+    {
+        self.terms
+            .iter()
+            .try_fold(V::zero(), |sum, term| Ok(sum + term.evaluate_with(lookup)?))
+    }
+}
+
 impl Traversable for Sum {
     fn child_iter<'a>(&'a self) -> Box<(dyn Iterator<Item = &'a Expression> + 'a)> {
         Box::new(self.terms.iter())
@@ -1,3 +1,5 @@
+use crate::{annotated_expression::AnnotationKind, PrintOpts, PrintTarget};
+
 #[derive(Debug)]
 pub(crate) struct TokenStream(Vec<MathPrintToken>);
 
@@ -10,8 +12,18 @@ impl FromIterator<MathPrintToken> for TokenStream {
 #[derive(Debug)]
 pub(crate) enum MathPrintToken {
     String(String),
-    AnnotationStart,
+    AnnotationStart {
+        kind: AnnotationKind,
+        label: Option<String>,
+    },
     AnnotationEnd,
+    /// Surrounds a parenthesized expression, including its parentheses.
+    /// Keeps lines from being wrapped inside parentheses, it doesn't print anything itself.
+    GroupStart,
+    GroupEnd,
+    /// A place where a long line can be broken, just before a `+` or `-` between terms of a sum.
+    /// Doesn't print anything unless the line is wrapped there.
+    BreakPoint,
 }
 
 impl IntoIterator for TokenStream {
@@ -45,51 +57,415 @@ impl std::fmt::Display for TokenStream {
     }
 }
 
+/// One line of MathPrint output, followed by lines marking its annotations.
+#[derive(Default)]
+struct MathLine {
+    text: String,
+    markers: String,
+    /// Labels to print under the line, along with the column their annotation starts at.
+    labels: Vec<(usize, String)>,
+    /// Labels of annotations that haven't printed anything other than spaces yet.
+    pending_labels: Vec<String>,
+    is_annotated: bool,
+}
+
+impl MathLine {
+    fn indented(indent: usize, is_annotated: bool) -> Self {
+        Self {
+            text: " ".repeat(indent),
+            markers: " ".repeat(indent),
+            labels: vec![],
+            pending_labels: vec![],
+            is_annotated,
+        }
+    }
+    /// Adds the tokens to the line, keeping track of which annotations are open.
+    fn push_tokens<'a>(
+        &mut self,
+        tokens: impl IntoIterator<Item = &'a MathPrintToken>,
+        open_annotations: &mut Vec<AnnotationKind>,
+    ) {
+        let mut starts_annotation = false;
+        for token in tokens {
+            match token {
+                MathPrintToken::String(string) => {
+                    // An operator starting a continued line doesn't need the space before it
+                    let string = if self.text.trim().is_empty() {
+                        string.trim_start()
+                    } else {
+                        string
+                    };
+                    // Spaces before an operator at the start of an annotation aren't part of it
+                    let leading_spaces = if self.pending_labels.is_empty() && !starts_annotation {
+                        0
+                    } else {
+                        string.chars().take_while(|c| *c == ' ').count()
+                    };
+                    if leading_spaces < string.chars().count() {
+                        let column = self.text.chars().count() + leading_spaces;
+                        self.labels
+                            .extend(self.pending_labels.drain(..).map(|label| (column, label)));
+                        starts_annotation = false;
+                    }
+                    self.text.push_str(string);
+                    let marker = open_annotations.last().map_or(' ', |kind| kind.marker());
+                    self.markers.push_str(&" ".repeat(leading_spaces));
+                    self.markers.push_str(
+                        &marker
+                            .to_string()
+                            .repeat(string.chars().count() - leading_spaces),
+                    );
+                }
+                MathPrintToken::AnnotationStart { kind, label } => {
+                    self.is_annotated = true;
+                    starts_annotation = true;
+                    open_annotations.push(*kind);
+                    self.pending_labels.extend(label.clone());
+                }
+                MathPrintToken::AnnotationEnd => {
+                    open_annotations.pop();
+                }
+                MathPrintToken::GroupStart
+                | MathPrintToken::GroupEnd
+                | MathPrintToken::BreakPoint => {}
+            }
+        }
+    }
+    fn render(self) -> String {
+        let mut lines = vec![self.text.trim_end().to_owned()];
+        if self.is_annotated {
+            lines.push(self.markers.trim_end().to_owned());
+        }
+        lines.extend(label_lines(self.labels));
+        lines.join("\n")
+    }
+}
+
+/// Lays out labels starting at the given columns,
+/// moving labels down to another line when they would run into the one before them.
+fn label_lines(labels: Vec<(usize, String)>) -> Vec<String> {
+    let mut lines: Vec<String> = vec![];
+    for (column, label) in labels {
+        let index = match lines.iter().position(|line| line.chars().count() < column) {
+            Some(index) => index,
+            None => {
+                lines.push(String::new());
+                lines.len() - 1
+            }
+        };
+        let line = &mut lines[index];
+        let padding = column - line.chars().count();
+        line.push_str(&" ".repeat(padding));
+        line.push_str(&label);
+    }
+    lines
+}
+
 pub(crate) fn math_print(token_stream: &TokenStream) -> String {
-    let mut math_line = String::new();
-    let mut annotation_line = String::new();
+    let mut line = MathLine::default();
+    line.push_tokens(&token_stream.0, &mut vec![]);
+    line.render()
+}
+
+fn latex_annotation_start(kind: AnnotationKind, label: Option<&str>) -> String {
+    let class = kind.css_class();
+    match label {
+        Some(_) => format!(r"\htmlClass{{{class}}}{{\underbrace{{"),
+        None => format!(r"\htmlClass{{{class}}}{{"),
+    }
+}
+
+fn latex_annotation_end(label: Option<&str>) -> String {
+    match label {
+        Some(label) => format!(r"}}_{{\text{{{}}}}}}}", escape_latex_text(label)),
+        None => "}".to_owned(),
+    }
+}
+
+pub(crate) fn latex_print(token_stream: &TokenStream) -> String {
+    let mut latex = String::new();
+    let mut labels = vec![];
+    for token in &token_stream.0 {
+        match token {
+            MathPrintToken::String(string) => latex.push_str(string),
+            MathPrintToken::AnnotationStart { kind, label } => {
+                latex.push_str(&latex_annotation_start(*kind, label.as_deref()));
+                labels.push(label.as_deref());
+            }
+            MathPrintToken::AnnotationEnd => {
+                latex.push_str(&latex_annotation_end(labels.pop().flatten()));
+            }
+            MathPrintToken::GroupStart | MathPrintToken::GroupEnd | MathPrintToken::BreakPoint => {}
+        }
+    }
+    latex
+}
+
+/// How far lines after the first are indented when an expression is wrapped.
+const WRAP_INDENT: usize = 4;
+
+/// The number of characters the tokens print, ignoring annotations.
+fn text_width(tokens: &[MathPrintToken]) -> usize {
+    tokens
+        .iter()
+        .map(|token| match token {
+            MathPrintToken::String(string) => string.chars().count(),
+            _ => 0,
+        })
+        .sum()
+}
+
+/// Splits the tokens at every break point that isn't inside parentheses.
+fn split_at_break_points(tokens: &[MathPrintToken]) -> Vec<&[MathPrintToken]> {
+    let mut runs = vec![];
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, token) in tokens.iter().enumerate() {
+        match token {
+            MathPrintToken::GroupStart => depth += 1,
+            MathPrintToken::GroupEnd => depth -= 1,
+            MathPrintToken::BreakPoint if depth == 0 => {
+                runs.push(&tokens[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    runs.push(&tokens[start..]);
+    runs
+}
+
+/// Packs as many runs onto each line as fit in `width`, accounting for the indentation of continued lines.
+/// A run that is wider than `width` by itself gets a line of its own.
+fn fill_lines(runs: Vec<&[MathPrintToken]>, width: usize) -> Vec<Vec<&[MathPrintToken]>> {
+    let mut lines: Vec<Vec<&[MathPrintToken]>> = vec![];
+    let mut line_width = 0;
+    for run in runs {
+        let run_width = text_width(run);
+        match lines.last_mut() {
+            Some(line) if line_width + run_width <= width => {
+                line.push(run);
+                line_width += run_width;
+            }
+            _ => {
+                line_width = if lines.is_empty() { 0 } else { WRAP_INDENT } + run_width;
+                lines.push(vec![run]);
+            }
+        }
+    }
+    lines
+}
 
-    let mut has_seen_annotation = false;
-    let mut is_annotation = false;
-    for token in token_stream.0.iter() {
+/// Like [`math_print`], but sums that don't fit in `width` characters are broken before top-level `+` and `-`,
+/// with continued lines indented.
+/// Each line is followed by its own annotation line if anything on it is annotated.
+pub(crate) fn math_print_wrapped(token_stream: &TokenStream, width: Option<usize>) -> String {
+    let width = match width {
+        Some(width) if text_width(&token_stream.0) > width => width,
+        _ => return math_print(token_stream),
+    };
+    let mut open_annotations = vec![];
+    fill_lines(split_at_break_points(&token_stream.0), width)
+        .into_iter()
+        .enumerate()
+        .map(|(i, runs)| {
+            let indent = if i == 0 { 0 } else { WRAP_INDENT };
+            let mut line = MathLine::indented(indent, !open_annotations.is_empty());
+            line.push_tokens(runs.into_iter().flatten(), &mut open_annotations);
+            line.render()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Like [`latex_print`], but sums that don't fit in `width` characters of LaTeX source
+/// are broken before top-level `+` and `-` into the lines of an `aligned` environment.
+pub(crate) fn latex_print_wrapped(token_stream: &TokenStream, width: Option<usize>) -> String {
+    let width = match width {
+        Some(width) if text_width(&token_stream.0) > width => width,
+        _ => return latex_print(token_stream),
+    };
+    // Each open annotation, and whether its label is shown on this line
+    let mut open_annotations: Vec<(AnnotationKind, Option<&str>, bool)> = vec![];
+    let lines: Vec<String> = fill_lines(split_at_break_points(&token_stream.0), width)
+        .into_iter()
+        .enumerate()
+        .map(|(i, runs)| {
+            let mut latex = String::from(if i == 0 { "&" } else { r"&\quad " });
+            // Annotations can't contain a line break, so they're closed at the end of each line
+            // and reopened on the next.
+            // Labels are placed under the first line of their annotation.
+            for (kind, _, is_labeled) in &mut open_annotations {
+                latex.push_str(&latex_annotation_start(*kind, None));
+                *is_labeled = false;
+            }
+            if i != 0 {
+                // The empty group keeps the operator starting the line binary
+                latex.push_str("{}");
+            }
+            for token in runs.into_iter().flatten() {
+                match token {
+                    MathPrintToken::String(string) => latex.push_str(string),
+                    MathPrintToken::AnnotationStart { kind, label } => {
+                        latex.push_str(&latex_annotation_start(*kind, label.as_deref()));
+                        open_annotations.push((*kind, label.as_deref(), true));
+                    }
+                    MathPrintToken::AnnotationEnd => {
+                        if let Some((_, label, is_labeled)) = open_annotations.pop() {
+                            latex.push_str(&latex_annotation_end(label.filter(|_| is_labeled)));
+                        }
+                    }
+                    MathPrintToken::GroupStart
+                    | MathPrintToken::GroupEnd
+                    | MathPrintToken::BreakPoint => {}
+                }
+            }
+            for (_, label, is_labeled) in open_annotations.iter().rev() {
+                latex.push_str(&latex_annotation_end(label.filter(|_| *is_labeled)));
+            }
+            latex
+        })
+        .collect();
+    format!(r"\begin{{aligned}}{}\end{{aligned}}", lines.join(r"\\"))
+}
+
+pub(crate) fn mathml_print(token_stream: &TokenStream) -> String {
+    let mut inner = String::new();
+    let mut labels = vec![];
+    for token in &token_stream.0 {
         match token {
-            MathPrintToken::String(string) => {
-                math_line.push_str(string);
-                if is_annotation {
-                    annotation_line.push_str(&"^".repeat(string.chars().count()));
-                } else {
-                    annotation_line.push_str(&" ".repeat(string.chars().count()));
+            MathPrintToken::String(string) => inner.push_str(string),
+            MathPrintToken::AnnotationStart { kind, label } => {
+                if label.is_some() {
+                    inner.push_str("<munder>");
                 }
+                inner.push_str(&format!(r#"<mrow class="{}">"#, kind.css_class()));
+                labels.push(label.as_deref());
             }
-            MathPrintToken::AnnotationStart => {
-                is_annotation = true;
-                has_seen_annotation = true;
+            MathPrintToken::AnnotationEnd => {
+                inner.push_str("</mrow>");
+                if let Some(label) = labels.pop().flatten() {
+                    inner.push_str(&format!("<mtext>{}</mtext></munder>", escape_html(label)));
+                }
+            }
+            MathPrintToken::GroupStart | MathPrintToken::GroupEnd | MathPrintToken::BreakPoint => {}
+        }
+    }
+    format!("<math>{inner}</math>")
+}
+
+/// Prints MathPrint tokens as HTML text, so pages can show expressions without running KaTeX.
+/// Annotations become `<span>`s with the annotation's class,
+/// and labels are placed under them by the `.labeled` styles.
+pub(crate) fn html_print(token_stream: &TokenStream) -> String {
+    let mut html = String::new();
+    let mut labels = vec![];
+    for token in &token_stream.0 {
+        match token {
+            MathPrintToken::String(string) => html.push_str(&escape_html(string)),
+            MathPrintToken::AnnotationStart { kind, label } => {
+                if label.is_some() {
+                    html.push_str(r#"<span class="labeled">"#);
+                }
+                html.push_str(&format!(r#"<span class="{}">"#, kind.css_class()));
+                labels.push(label.as_deref());
             }
             MathPrintToken::AnnotationEnd => {
-                is_annotation = false;
+                html.push_str("</span>");
+                if let Some(label) = labels.pop().flatten() {
+                    html.push_str(&format!(
+                        r#"<span class="label">{}</span></span>"#,
+                        escape_html(label)
+                    ));
+                }
             }
+            MathPrintToken::GroupStart | MathPrintToken::GroupEnd | MathPrintToken::BreakPoint => {}
         }
     }
+    format!(r#"<span class="expression">{html}</span>"#)
+}
 
-    if has_seen_annotation {
-        format!("{}\n{}", math_line, annotation_line.trim_end())
-    } else {
-        math_line
+pub(crate) fn typst_print(token_stream: &TokenStream) -> String {
+    let mut typst = String::new();
+    let mut labels = vec![];
+    for token in &token_stream.0 {
+        match token {
+            MathPrintToken::String(string) => typst.push_str(string),
+            MathPrintToken::AnnotationStart { kind, label } => {
+                if label.is_some() {
+                    typst.push_str("underbrace(");
+                }
+                match kind.typst_fill() {
+                    Some(fill) => typst.push_str(&format!("#highlight(fill: {fill})[$")),
+                    None => typst.push_str("#highlight[$"),
+                }
+                labels.push(label.as_deref());
+            }
+            MathPrintToken::AnnotationEnd => {
+                typst.push_str("$]");
+                if let Some(label) = labels.pop().flatten() {
+                    typst.push_str(&format!(
+                        r#", "{}")"#,
+                        label.replace('\\', r"\\").replace('"', r#"\""#)
+                    ));
+                }
+            }
+            MathPrintToken::GroupStart | MathPrintToken::GroupEnd | MathPrintToken::BreakPoint => {}
+        }
     }
+    typst
 }
 
-pub(crate) fn latex_print(token_stream: &TokenStream) -> String {
-    token_stream
-        .0
-        .iter()
-        .map(|token| match token {
-            MathPrintToken::String(string) => string,
-            MathPrintToken::AnnotationStart => r#"\htmlClass{hl}{"#,
-            MathPrintToken::AnnotationEnd => "}",
+/// Prints the tokens with the printer for the target,
+/// wrapping MathPrint and LaTeX to [`PrintOpts::line_width`].
+pub(crate) fn print_for_target(
+    token_stream: &TokenStream,
+    target: PrintTarget,
+    print_opts: &PrintOpts,
+) -> String {
+    match target {
+        PrintTarget::MathPrint => math_print_wrapped(token_stream, print_opts.line_width),
+        PrintTarget::LaTex => latex_print_wrapped(token_stream, print_opts.line_width),
+        PrintTarget::MathML => mathml_print(token_stream),
+        PrintTarget::Typst => typst_print(token_stream),
+    }
+}
+
+/// Escapes text so it can be placed inside LaTeX's `\text{}`.
+fn escape_latex_text(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '\\' => r"\textbackslash{}".to_owned(),
+            '~' => r"\textasciitilde{}".to_owned(),
+            '^' => r"\textasciicircum{}".to_owned(),
+            '{' | '}' | '$' | '&' | '#' | '_' | '%' => format!("\\{c}"),
+            c => c.to_string(),
+        })
+        .collect()
+}
+
+/// Escapes text so it can be placed in Typst markup without being interpreted as syntax.
+pub(crate) fn escape_typst(text: &str) -> String {
+    text.chars()
+        .flat_map(|c| {
+            let escape = matches!(
+                c,
+                '\\' | '*' | '_' | '#' | '$' | '[' | ']' | '<' | '>' | '@' | '`' | '~' | '/'
+            );
+            escape.then_some('\\').into_iter().chain(std::iter::once(c))
         })
         .collect()
 }
 
+/// Escapes text so it can be placed inside an HTML or MathML element.
+pub(crate) fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[doc(hidden)]
 #[macro_export]
 macro_rules! token_iter {
     ($exp:literal) => {{
@@ -103,6 +479,7 @@ macro_rules! token_iter {
 }
 
 // TODO: most of these don't need to use iterators
+#[doc(hidden)]
 #[macro_export]
 macro_rules! tokens {
     ($exp:literal) => {{
@@ -119,3 +496,72 @@ macro_rules! tokens {
         TokenStream::from_iter(token_iter!($exp1)$(.chain(token_iter!($exp)))*)
     }};
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        annotated_expression::{AnnotatedExpression, Annotation},
+        constant::Constant,
+        expression::{AsExpression, DEFAULT_PRINT_OPTS},
+        math, PrintOpts, PrintTarget,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_wrapping() {
+        let a = Constant::new("a");
+        let b = Constant::new("b");
+        let x = Constant::new("x");
+        let y = Constant::new("y");
+        let pi = Constant::new(r"\pi");
+
+        let inner = math![(pi * x) + y].expr();
+        let exp =
+            math![(a * b) + (x + y) + { inner.clone() } + (-(a * (x - y))) + (pi * x * y)].expr();
+        let annotated = AnnotatedExpression {
+            expression: exp,
+            annotations: vec![Annotation::new(&inner)],
+        };
+
+        let tokens = annotated.print(PrintTarget::MathPrint, &DEFAULT_PRINT_OPTS);
+        assert_eq!(math_print_wrapped(&tokens, Some(100)), math_print(&tokens));
+        insta::assert_snapshot!(math_print_wrapped(&tokens, Some(24)), @r###"
+        a * b + (x + y)
+            + (π * x + y)
+               ^^^^^^^^^
+            - a * (x - y)
+            + π * x * y
+        "###);
+
+        let latex_opts = PrintOpts::default_for(PrintTarget::LaTex);
+        let tokens = annotated.print(PrintTarget::LaTex, &latex_opts);
+        assert_eq!(latex_print_wrapped(&tokens, None), latex_print(&tokens));
+        insta::assert_snapshot!(latex_print_wrapped(&tokens, Some(40)), @r###"\begin{aligned}&a b+\left(x+y\right)\\&\quad {}+\left(\htmlClass{hl}{\pi x+y}\right)\\&\quad {}-a \left(x-y\right)+\pi x y\end{aligned}"###);
+    }
+
+    #[test]
+    fn test_wrapping_annotation_across_lines() {
+        let a = Constant::new("a");
+        let b = Constant::new("b");
+        let c = Constant::new("c");
+
+        let exp = math![a + b + c].expr();
+        let annotated = AnnotatedExpression {
+            expression: exp.clone(),
+            annotations: vec![Annotation::new(&exp)],
+        };
+        let tokens = annotated.print(PrintTarget::MathPrint, &DEFAULT_PRINT_OPTS);
+        insta::assert_snapshot!(math_print_wrapped(&tokens, Some(5)), @r###"
+        a + b
+        ^^^^^
+            + c
+            ^^^
+        "###);
+        let tokens = annotated.print(
+            PrintTarget::LaTex,
+            &PrintOpts::default_for(PrintTarget::LaTex),
+        );
+        insta::assert_snapshot!(latex_print_wrapped(&tokens, Some(3)), @r###"\begin{aligned}&\htmlClass{hl}{a+b}\\&\quad \htmlClass{hl}{{}+c}\end{aligned}"###);
+    }
+}
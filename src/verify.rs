@@ -0,0 +1,213 @@
+use std::collections::BTreeSet;
+
+use crate::{
+    constant::latex_to_unicode, evaluate::Bindings, expression::Expression, step::Step,
+    traverse::Traversable,
+};
+
+/// How many random assignments each step is checked at.
+const TRIALS: usize = 32;
+/// Random values are picked from `-RANGE..RANGE`.
+const RANGE: f64 = 10.0;
+/// The relative difference allowed between both sides, to account for floating point error.
+const TOLERANCE: f64 = 1e-9;
+
+/// A step whose result doesn't evaluate to the same value as the expression it started from.
+pub struct UnsoundStep {
+    pub label: Option<String>,
+    pub before: Expression,
+    pub after: Expression,
+    /// Variable values where `before` and `after` differ.
+    pub counterexample: Bindings,
+}
+
+impl std::fmt::Display for UnsoundStep {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.label {
+            Some(label) => writeln!(f, "Unsound step: {label}")?,
+            None => writeln!(f, "Unsound step")?,
+        }
+        let before = self.before.evaluate(&self.counterexample).unwrap();
+        let after = self.after.evaluate(&self.counterexample).unwrap();
+        writeln!(f, "  {}  =  {before}", self.before)?;
+        writeln!(f, "  {}  =  {after}", self.after)?;
+        write!(f, "  when {}", format_bindings(&self.counterexample))
+    }
+}
+
+impl std::fmt::Debug for UnsoundStep {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{self}")
+    }
+}
+
+fn format_bindings(bindings: &Bindings) -> String {
+    let mut bindings: Vec<_> = bindings
+        .iter()
+        .filter(|(name, _)| !matches!(*name, r"\pi" | "e"))
+        .collect();
+    bindings.sort_by_key(|(name, _)| *name);
+    bindings
+        .iter()
+        .map(|(name, value)| format!("{} = {value}", latex_to_unicode(name).unwrap_or(name)))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Checks that every step (and all of its substeps) produces a result equal to what it started with,
+/// by evaluating both at random values for every variable.
+/// Substeps are checked before the step containing them,
+/// so the reported step is the most specific one that went wrong.
+pub fn verify(step: &Step) -> Result<(), UnsoundStep> {
+    for substep in &step.substeps {
+        verify(substep)?;
+    }
+    let before = match step_input(step) {
+        Some(before) => before,
+        None => return Ok(()),
+    };
+    match find_counterexample(before, &step.result) {
+        Some(counterexample) => Err(UnsoundStep {
+            label: step.label.clone(),
+            before: before.clone(),
+            after: step.result.clone(),
+            counterexample,
+        }),
+        None => Ok(()),
+    }
+}
+
+/// The expression a step starts from.
+/// Steps which only group substeps start where their first substep starts.
+fn step_input(step: &Step) -> Option<&Expression> {
+    match &step.annotated_expression {
+        Some(annotated_expression) => Some(&annotated_expression.expression),
+        None => step.substeps.first().and_then(step_input),
+    }
+}
+
+/// Evaluates both expressions at random values for their variables,
+/// returning the first set of values where they differ.
+/// `π` and `e` keep their usual values.
+pub fn find_counterexample(a: &Expression, b: &Expression) -> Option<Bindings> {
+    let mut names = BTreeSet::new();
+    collect_constant_names(a, &mut names);
+    collect_constant_names(b, &mut names);
+    names.remove(r"\pi");
+    names.remove("e");
+
+    let mut rng = Rng::new(0x5eed);
+    (0..TRIALS).find_map(|_| {
+        let bindings = names.iter().fold(Bindings::new(), |bindings, name| {
+            bindings.bind(name, rng.range(-RANGE, RANGE))
+        });
+        let a_value = a.evaluate(&bindings).unwrap();
+        let b_value = b.evaluate(&bindings).unwrap();
+        let scale = a_value.abs().max(b_value.abs()).max(1.0);
+        if (a_value - b_value).abs() > TOLERANCE * scale {
+            Some(bindings)
+        } else {
+            None
+        }
+    })
+}
+
+fn collect_constant_names(expr: &Expression, names: &mut BTreeSet<String>) {
+    if let Expression::Constant(constant) = expr {
+        names.insert(constant.name().to_owned());
+    }
+    for child in expr.child_iter() {
+        collect_constant_names(child, names);
+    }
+}
+
+/// SplitMix64: a tiny pseudo-random number generator.
+/// It is seeded with a fixed value so checks are reproducible.
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+    /// A random number in `min..max`.
+    fn range(&mut self, min: f64, max: f64) -> f64 {
+        let unit = (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64;
+        min + unit * (max - min)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        annotated_expression::{AnnotatedExpression, Annotation},
+        constant::Constant,
+        expression::AsExpression,
+        math,
+        simplify::simplify_excess_parens,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_verify_sound_steps() {
+        let x = Constant::new("x");
+        let y = Constant::new("y");
+        let z = Constant::new("z");
+
+        let exp = math![((x + y) + y) * (x * y) * ((z * x) + y)].expr();
+        assert!(verify(&simplify_excess_parens(&exp)).is_ok());
+    }
+
+    #[test]
+    fn test_verify_unsound_steps() {
+        let x = Constant::new("x");
+        let y = Constant::new("y");
+        let pi = Constant::new(r"\pi");
+
+        let inner = math![x + y].expr();
+        let before = math![pi * { inner.clone() }].expr();
+        let step = Step {
+            label: Some("Simplify".to_owned()),
+            annotated_expression: None,
+            substeps: vec![
+                Step {
+                    label: Some("Remove excess parentheses around product".to_owned()),
+                    annotated_expression: Some(AnnotatedExpression {
+                        expression: before.clone(),
+                        annotations: vec![],
+                    }),
+                    substeps: vec![],
+                    result: before.clone(),
+                    result_annotations: vec![],
+                },
+                Step {
+                    label: Some("Distribute π".to_owned()),
+                    annotated_expression: Some(AnnotatedExpression {
+                        expression: before,
+                        annotations: vec![Annotation::new(&inner)],
+                    }),
+                    substeps: vec![],
+                    result: math![(pi * x) + y].expr(),
+                    result_annotations: vec![],
+                },
+            ],
+            result: math![(pi * x) + y].expr(),
+            result_annotations: vec![],
+        };
+        let unsound = verify(&step).unwrap_err();
+        assert_eq!(unsound.label.as_deref(), Some("Distribute π"));
+        insta::assert_display_snapshot!(unsound, @r###"
+        Unsound step: Distribute π
+          π * (x + y)  =  -39.480404714830534
+          π * x + y  =  -32.318966370590616
+          when x = -9.223025306056297, y = -3.343977825211404
+        "###);
+    }
+}
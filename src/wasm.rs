@@ -0,0 +1,57 @@
+//! Bindings for the demo app, which runs the engine in the browser.
+//!
+//! The `katex` crate runs KaTeX in duktape, which can't be compiled to WebAssembly,
+//! so build without it:
+//!
+//! ```sh
+//! cargo build --lib --release --target wasm32-unknown-unknown --no-default-features --features wasm
+//! wasm-bindgen --target web --out-dir demo/src/wasm target/wasm32-unknown-unknown/release/math_thing.wasm
+//! ```
+//!
+//! Expressions can then be rendered in Rust as MathML,
+//! or in the browser by passing the `latex` from the JSON to KaTeX.
+
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    json::{expression_to_json, step_to_json},
+    simplify::simplify_excess_parens,
+    step::StepPrintOpts,
+    PrintOpts, PrintTarget,
+};
+
+/// Parses an infix expression, returning it as JSON matching
+/// `#/$defs/renderedExpression` in `schema/step.schema.json`.
+#[wasm_bindgen]
+pub fn parse(input: &str) -> Result<String, JsError> {
+    Ok(expression_to_json(&crate::parse(input)?))
+}
+
+/// Simplifies an infix expression, returning the result as JSON like [`parse`].
+#[wasm_bindgen]
+pub fn simplify(input: &str) -> Result<String, JsError> {
+    let steps = simplify_excess_parens(&crate::parse(input)?);
+    Ok(expression_to_json(&steps.result))
+}
+
+/// Simplifies an infix expression, returning the steps in `format`:
+///
+/// - `json`: JSON matching `schema/step.schema.json`, to render in the browser.
+/// - `mathml`: HTML with the expressions as MathML.
+/// - `text`: HTML with the expressions as styled MathPrint text.
+#[wasm_bindgen(js_name = renderSteps)]
+pub fn render_steps(input: &str, format: &str) -> Result<String, JsError> {
+    let steps = simplify_excess_parens(&crate::parse(input)?);
+    let html = |target| {
+        steps.html_print(
+            target,
+            &StepPrintOpts::uniform(PrintOpts::default_for(target)),
+        )
+    };
+    match format {
+        "json" => Ok(step_to_json(&steps)),
+        "mathml" => Ok(html(PrintTarget::MathML)),
+        "text" => Ok(html(PrintTarget::MathPrint)),
+        _ => Err(JsError::new(&format!("Unknown format {format:?}"))),
+    }
+}